@@ -1,21 +1,48 @@
 #![allow(clippy::blocks_in_conditions)]
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
 
 use async_trait::async_trait;
+use clap::ValueEnum;
 use coerce::actor::context::ActorContext;
 use coerce::actor::message::Handler;
 use coerce::actor::{Actor, ActorId, IntoActorId, LocalActorRef};
+use serde::{Deserialize, Serialize};
 use solana_client::{
     nonblocking::rpc_client::RpcClient,
     rpc_config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter},
 };
-use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::{
+    address_lookup_table::AddressLookupTableAccount, commitment_config::CommitmentConfig,
+    pubkey::Pubkey,
+};
+use tokio::sync::Notify;
 
 use crate::actors::listener::utils::get_pool_init_infos;
+use crate::actors::listener::wallet_pool::WalletPool;
 use crate::actors::swapper::actor::{PoolInitTxInfos, Swapper};
+use crate::actors::swapper::control::{
+    SwapperControl, SwapperSnapshot, SwapperState, SwapperTransaction,
+};
+use crate::journal::Journal;
+use crate::latency::{LaunchLatency, Stage};
 use crate::message;
+use crate::metrics::{
+    rpc_errors_total, POOLS_DETECTED, POOLS_FILTERED, SWAPPERS_SPAWNED, WEBSOCKET_RECONNECTS,
+};
+use crate::positions::{Position, PositionStore};
+use crate::recording::{recorder::Recorder, RecordedEvent};
+use crate::sender::paper::PaperEngine;
+use crate::signer::TransactionSigner;
+use crate::utils::now_ms;
 use crate::{
-    constants::CREATE_POOL_FEE_ACCOUNT_ADDRESS,
+    constants::{CREATE_POOL_FEE_ACCOUNT_ADDRESS, LAMPORTS_PER_SOL, WALLET_FEE_BUFFER_LAMPORTS},
     types::ProgramConfig,
     websocket::{LogsSubscribeResponse, WebSocket, WebSocketConfig},
 };
@@ -23,69 +50,378 @@ use crate::{
 pub struct Listener {
     config: ProgramConfig,
     client: Arc<RpcClient>,
+    wallet_pool: WalletPool,
     max_swappers: u8,
     trade_amount: f64,
+    /// Shared by the swappers in paper mode
+    paper_engine: Option<Arc<PaperEngine>>,
+    /// Captures detections for offline replays, see `--record`
+    recorder: Option<Arc<Recorder>>,
+    /// Open positions are persisted and resumed on startup, see `POSITIONS_DB_PATH`
+    position_store: Option<Arc<PositionStore>>,
+    /// Shared by the swappers, see `JOURNAL_DB_PATH`
+    journal: Option<Arc<Journal>>,
+    /// Loaded once at startup, see `LOOKUP_TABLE_ADDRESS`
+    lookup_tables: Vec<AddressLookupTableAccount>,
+    /// Running swappers
+    swappers: HashMap<ActorId, Arc<SwapperControl>>,
+    /// No new entries are taken while paused
+    paused: bool,
+    /// Set once shutting down, see [`Shutdown`]
+    shutdown_policy: Option<ShutdownPolicy>,
+    /// Pools with these mints are not traded
+    blacklist: HashSet<Pubkey>,
+    /// Latest detected pools first
+    feed: VecDeque<PoolVerdict>,
+    /// Latest transactions of the exited swappers first
+    recent_transactions: VecDeque<SwapperTransaction>,
+    /// Shared with the listen routine
+    health: Arc<ConnectionHealth>,
+    /// Notified to exit without waiting for the swappers, see
+    /// [`ShutdownPolicy::Persist`]
+    exit_notification: Option<Arc<Notify>>,
 }
 
+/// Pools kept in the feed, oldest are dropped first
+const FEED_LEN: usize = 50;
+/// Transactions of exited swappers kept in the status
+const RECENT_TRANSACTIONS_LEN: usize = 20;
+
+/// Pool to trade and the stages its launch went through so far
 #[derive(Debug, Clone)]
-struct SpawnSwapper(PoolInitTxInfos);
+struct SpawnSwapper(PoolInitTxInfos, LaunchLatency);
 message!(SpawnSwapper, Result<(), eyre::Error>);
 
 #[async_trait]
 impl Handler<SpawnSwapper> for Listener {
-    #[tracing::instrument(skip_all, err)]
+    #[tracing::instrument(skip_all, err, fields(amm_id = %message.0.amm_id))]
     async fn handle(
         &mut self,
         message: SpawnSwapper,
         ctx: &mut ActorContext,
     ) -> Result<(), eyre::Error> {
+        let SpawnSwapper(init_pool_tx_infos, latency) = message;
+
+        if self.paused {
+            tracing::info!("entries paused");
+            POOLS_FILTERED.with_label_values(&["paused"]).inc();
+            self.record_verdict(&init_pool_tx_infos, "paused");
+            return Ok(());
+        }
+
+        if self.blacklist.contains(&init_pool_tx_infos.base_mint)
+            || self.blacklist.contains(&init_pool_tx_infos.quote_mint)
+        {
+            tracing::info!("mint blacklisted");
+            POOLS_FILTERED.with_label_values(&["blacklisted"]).inc();
+            self.record_verdict(&init_pool_tx_infos, "blacklisted");
+            return Ok(());
+        }
+
         let amount_swappers = ctx.supervised_count();
         if amount_swappers >= self.max_swappers as usize {
             tracing::info!("max swappers reached");
+            POOLS_FILTERED.with_label_values(&["max_swappers"]).inc();
+            self.record_verdict(&init_pool_tx_infos, "max_swappers");
             return Ok(());
         }
 
-        let init_pool_tx_infos = message.0;
-        let swapper = Swapper::from_pool_params(
-            Arc::clone(&self.client),
-            self.config.clone(),
+        let id = swapper_id(&init_pool_tx_infos);
+
+        // Paper trades are paid from the virtual balance, not the wallet
+        let min_lamports = match self.paper_engine {
+            Some(_) => 0,
+            None => (self.trade_amount * *LAMPORTS_PER_SOL) as u64 + WALLET_FEE_BUFFER_LAMPORTS,
+        };
+        let Some(wallet) = self.wallet_pool.checkout(id.clone(), min_lamports) else {
+            tracing::info!("no funded wallet available");
+            POOLS_FILTERED.with_label_values(&["no_wallet"]).inc();
+            self.record_verdict(&init_pool_tx_infos, "no_wallet");
+            return Ok(());
+        };
+        self.record_verdict(&init_pool_tx_infos, "spawned");
+
+        self.spawn_swapper(
+            ctx,
+            id,
+            wallet,
             init_pool_tx_infos,
             self.trade_amount,
+            SwapperStart::Buy(latency),
         )
-        .await?;
+        .await
+    }
+}
 
-        let id = format!(
-            "swapper-{}{}",
-            &init_pool_tx_infos.market_id.to_string()[..6],
-            &init_pool_tx_infos.amm_id.to_string()[..6],
-        );
-        tracing::info!(
-            "spawned swapper with id {}, market id {:?}, amm id {:?}, base_mint {:?}, quote_mint {:?}",
-            id,
-            init_pool_tx_infos.market_id,
-            init_pool_tx_infos.amm_id,
-            init_pool_tx_infos.base_mint,
-            init_pool_tx_infos.quote_mint,
-        );
+/// Detected pool and what became of it
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolVerdict {
+    pub at_ms: u64,
+    pub amm_id: String,
+    pub base_mint: String,
+    pub quote_mint: String,
+    /// `spawned` while its swapper runs, then `bought`, `failed` or the
+    /// filter that skipped the pool
+    pub verdict: &'static str,
+}
 
-        ctx.spawn_deferred(id.into_actor_id(), swapper)?;
+/// Logs subscription state, updated by the listen routine
+#[derive(Debug, Default)]
+pub struct ConnectionHealth {
+    websocket_connected: AtomicBool,
+    last_log_at_ms: AtomicU64,
+}
 
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthStatus {
+    pub websocket_connected: bool,
+    pub last_log_at_ms: Option<u64>,
+    pub websocket_reconnects: u64,
+    pub rpc_errors: u64,
+}
+
+/// Settings, running swappers and recent activity, see the control API
+#[derive(Debug, Clone, Serialize)]
+pub struct ListenerStatus {
+    pub paused: bool,
+    pub shutdown_policy: Option<ShutdownPolicy>,
+    pub max_swappers: u8,
+    pub trade_amount: f64,
+    pub blacklist: Vec<String>,
+    pub swappers: Vec<SwapperSnapshot>,
+    /// Latest first
+    pub feed: Vec<PoolVerdict>,
+    /// Latest first
+    pub transactions: Vec<SwapperTransaction>,
+    pub health: HealthStatus,
+}
+
+#[derive(Debug, Clone)]
+pub struct GetStatus;
+message!(GetStatus, ListenerStatus);
+
+#[async_trait]
+impl Handler<GetStatus> for Listener {
+    async fn handle(&mut self, _message: GetStatus, _ctx: &mut ActorContext) -> ListenerStatus {
+        let mut swappers: Vec<SwapperSnapshot> = self
+            .swappers
+            .values()
+            .map(|control| control.snapshot())
+            .collect();
+        swappers.sort_by_key(|snapshot| snapshot.opened_at_ms);
+
+        let mut transactions: Vec<SwapperTransaction> = swappers
+            .iter()
+            .flat_map(|snapshot| snapshot.transactions.iter().cloned())
+            .chain(self.recent_transactions.iter().cloned())
+            .collect();
+        transactions.sort_by_key(|transaction| std::cmp::Reverse(transaction.at_ms));
+        transactions.truncate(RECENT_TRANSACTIONS_LEN);
+
+        let last_log_at_ms = self.health.last_log_at_ms.load(Ordering::Relaxed);
+        ListenerStatus {
+            paused: self.paused,
+            shutdown_policy: self.shutdown_policy,
+            max_swappers: self.max_swappers,
+            trade_amount: self.trade_amount,
+            blacklist: self.blacklist.iter().map(Pubkey::to_string).collect(),
+            swappers,
+            feed: self.feed.iter().cloned().collect(),
+            transactions,
+            health: HealthStatus {
+                websocket_connected: self.health.websocket_connected.load(Ordering::Relaxed),
+                last_log_at_ms: (last_log_at_ms > 0).then_some(last_log_at_ms),
+                websocket_reconnects: WEBSOCKET_RECONNECTS.get(),
+                rpc_errors: rpc_errors_total(),
+            },
+        }
+    }
+}
+
+/// Stops or restarts taking new entries, running swappers carry on
+#[derive(Debug, Clone)]
+pub struct SetPaused(pub bool);
+message!(SetPaused, ());
+
+#[async_trait]
+impl Handler<SetPaused> for Listener {
+    #[tracing::instrument(skip_all, fields(paused = message.0))]
+    async fn handle(&mut self, message: SetPaused, _ctx: &mut ActorContext) {
+        tracing::info!("setting paused");
+        self.paused = message.0;
+    }
+}
+
+/// Settings applied to the swappers spawned from now on
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpdateSettings {
+    pub max_swappers: Option<u8>,
+    pub trade_amount: Option<f64>,
+}
+message!(UpdateSettings, Result<(), eyre::Error>);
+
+#[async_trait]
+impl Handler<UpdateSettings> for Listener {
+    #[tracing::instrument(skip_all, err)]
+    async fn handle(
+        &mut self,
+        message: UpdateSettings,
+        _ctx: &mut ActorContext,
+    ) -> Result<(), eyre::Error> {
+        if let Some(trade_amount) = message.trade_amount {
+            if !trade_amount.is_finite() || trade_amount <= 0. {
+                return Err(eyre::eyre!("trade amount must be positive"));
+            }
+            tracing::info!("setting trade amount to {}", trade_amount);
+            self.trade_amount = trade_amount;
+        }
+        if let Some(max_swappers) = message.max_swappers {
+            tracing::info!("setting max swappers to {}", max_swappers);
+            self.max_swappers = max_swappers;
+        }
+        Ok(())
+    }
+}
+
+/// Has the swapper of this pool sell its position at its next price check
+#[derive(Debug, Clone)]
+pub struct ForceSell(pub Pubkey);
+message!(ForceSell, Result<(), eyre::Error>);
+
+#[async_trait]
+impl Handler<ForceSell> for Listener {
+    #[tracing::instrument(skip_all, err, fields(amm_id = %message.0))]
+    async fn handle(
+        &mut self,
+        message: ForceSell,
+        _ctx: &mut ActorContext,
+    ) -> Result<(), eyre::Error> {
+        let amm_id = message.0.to_string();
+        let control = self
+            .swappers
+            .values()
+            .find(|control| {
+                let snapshot = control.snapshot();
+                snapshot.amm_id == amm_id && snapshot.state != SwapperState::Exited
+            })
+            .ok_or_else(|| eyre::eyre!("no running swapper for amm id {}", amm_id))?;
+        tracing::info!("requesting sell");
+        control.request_sell();
         Ok(())
     }
 }
 
+/// Skips the pools of this mint from now on and sells the positions held in it
+#[derive(Debug, Clone)]
+pub struct Blacklist(pub Pubkey);
+message!(Blacklist, ());
+
+#[async_trait]
+impl Handler<Blacklist> for Listener {
+    #[tracing::instrument(skip_all, fields(mint = %message.0))]
+    async fn handle(&mut self, message: Blacklist, _ctx: &mut ActorContext) {
+        tracing::info!("blacklisting mint");
+        let mint = message.0.to_string();
+        for control in self.swappers.values() {
+            if control.snapshot().mint == mint {
+                control.request_sell();
+            }
+        }
+        self.blacklist.insert(message.0);
+    }
+}
+
+/// What becomes of the open positions on shutdown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum ShutdownPolicy {
+    /// Wait for the swappers to exit on their strategy
+    Drain,
+    /// Sell every position at its next price check
+    Liquidate,
+    /// Exit right away, persisted positions are resumed on the next start
+    Persist,
+}
+
+/// Stops taking new entries and stops the listener once the running
+/// swappers have exited their positions, or right away with
+/// [`ShutdownPolicy::Persist`]
+#[derive(Debug, Clone)]
+pub struct Shutdown(pub ShutdownPolicy);
+message!(Shutdown, ());
+
+#[async_trait]
+impl Handler<Shutdown> for Listener {
+    #[tracing::instrument(skip_all, fields(policy = ?message.0))]
+    async fn handle(&mut self, message: Shutdown, ctx: &mut ActorContext) {
+        if self.shutdown_policy == Some(ShutdownPolicy::Persist) {
+            return;
+        }
+        self.paused = true;
+        self.shutdown_policy = Some(message.0);
+        match message.0 {
+            ShutdownPolicy::Drain => {}
+            ShutdownPolicy::Liquidate => {
+                for control in self.swappers.values() {
+                    control.request_sell();
+                }
+            }
+            // Swappers run their trade in `started`, stopping the listener
+            // would wait for them
+            ShutdownPolicy::Persist => {
+                tracing::info!("exiting with {} swappers running", self.swappers.len());
+                if let Some(exit_notification) = &self.exit_notification {
+                    exit_notification.notify_one();
+                }
+                return;
+            }
+        }
+
+        if self.swappers.is_empty() {
+            tracing::info!("stopping listener");
+            ctx.stop(None);
+        } else {
+            tracing::info!(
+                "stopping listener after {} swappers exit",
+                self.swappers.len()
+            );
+        }
+    }
+}
+
 #[async_trait]
 impl Actor for Listener {
     #[tracing::instrument(skip_all)]
     async fn started(&mut self, ctx: &mut ActorContext) {
         tracing::info!("listener");
 
+        if let Err(e) = self.wallet_pool.refresh_balances().await {
+            tracing::error!("failed to get wallet balances: {:?}", e);
+        }
+        let wallet_count = self.wallet_pool.wallet_count();
+        if wallet_count > 1 && wallet_count < self.max_swappers as usize {
+            tracing::warn!(
+                "{} wallets for {} max swappers, pools detected while every wallet is in use are skipped",
+                wallet_count,
+                self.max_swappers
+            );
+        }
+        self.resume_open_positions(ctx).await;
         self.listen_and_notify_spawn_swappers(ctx);
     }
 
-    #[tracing::instrument(skip_all, fields(id = %_id))]
-    async fn on_child_stopped(&mut self, _id: &ActorId, _ctx: &mut ActorContext) {
+    #[tracing::instrument(skip_all, fields(id = %id))]
+    async fn on_child_stopped(&mut self, id: &ActorId, ctx: &mut ActorContext) {
         tracing::info!("listener child stopped");
+        self.wallet_pool.release(id);
+        if let Some(control) = self.swappers.remove(id) {
+            self.record_exit(control.snapshot());
+        }
+
+        if self.shutdown_policy.is_some() && self.swappers.is_empty() {
+            tracing::info!("stopping listener: swappers exited");
+            ctx.stop(None);
+        }
     }
 }
 
@@ -93,14 +429,189 @@ impl Listener {
     pub fn new(
         client: Arc<RpcClient>,
         config: ProgramConfig,
+        wallets: Vec<Arc<dyn TransactionSigner>>,
         max_swappers: u8,
         trade_amount: f64,
+        paper_engine: Option<Arc<PaperEngine>>,
+        recorder: Option<Arc<Recorder>>,
     ) -> Self {
         Self {
+            wallet_pool: WalletPool::new(Arc::clone(&client), wallets),
+            journal: Journal::from_config(&config),
+            lookup_tables: vec![],
+            swappers: HashMap::new(),
+            paused: false,
+            shutdown_policy: None,
+            blacklist: HashSet::new(),
+            feed: VecDeque::new(),
+            recent_transactions: VecDeque::new(),
+            health: Arc::new(ConnectionHealth::default()),
+            exit_notification: None,
             client,
             config,
             max_swappers,
             trade_amount,
+            paper_engine,
+            recorder,
+            position_store: None,
+        }
+    }
+
+    /// Persists positions to `position_store` and resumes its open ones
+    pub fn with_position_store(mut self, position_store: Option<Arc<PositionStore>>) -> Self {
+        self.position_store = position_store;
+        self
+    }
+
+    /// Has the swappers resolve accounts through `lookup_tables`
+    pub fn with_lookup_tables(mut self, lookup_tables: Vec<AddressLookupTableAccount>) -> Self {
+        self.lookup_tables = lookup_tables;
+        self
+    }
+
+    /// Notifies `exit_notification` to exit without stopping the listener
+    pub fn with_exit_notification(mut self, exit_notification: Arc<Notify>) -> Self {
+        self.exit_notification = Some(exit_notification);
+        self
+    }
+
+    fn record_verdict(&mut self, init_pool_tx_infos: &PoolInitTxInfos, verdict: &'static str) {
+        self.feed.push_front(PoolVerdict {
+            at_ms: now_ms(),
+            amm_id: init_pool_tx_infos.amm_id.to_string(),
+            base_mint: init_pool_tx_infos.base_mint.to_string(),
+            quote_mint: init_pool_tx_infos.quote_mint.to_string(),
+            verdict,
+        });
+        self.feed.truncate(FEED_LEN);
+    }
+
+    /// Settles the feed verdict of an exited swapper and keeps its transactions
+    fn record_exit(&mut self, snapshot: SwapperSnapshot) {
+        let verdict = match snapshot.filter_reason {
+            Some(reason) => reason,
+            None if snapshot.entry_lamports.is_some() => "bought",
+            None => "failed",
+        };
+        if let Some(pool) = self
+            .feed
+            .iter_mut()
+            .find(|pool| pool.amm_id == snapshot.amm_id && pool.verdict == "spawned")
+        {
+            pool.verdict = verdict;
+        }
+
+        for transaction in snapshot.transactions.into_iter().rev() {
+            self.recent_transactions.push_front(transaction);
+        }
+        self.recent_transactions.truncate(RECENT_TRANSACTIONS_LEN);
+    }
+
+    /// Spawns a swapper on the wallet checked out under `id`, releasing the
+    /// wallet when it fails to start
+    async fn spawn_swapper(
+        &mut self,
+        ctx: &mut ActorContext,
+        id: ActorId,
+        wallet: Arc<dyn TransactionSigner>,
+        init_pool_tx_infos: PoolInitTxInfos,
+        trade_amount: f64,
+        start: SwapperStart,
+    ) -> Result<(), eyre::Error> {
+        let swapper = Swapper::from_pool_params(
+            Arc::clone(&self.client),
+            self.config.clone(),
+            Arc::clone(&wallet),
+            init_pool_tx_infos,
+            trade_amount,
+        )
+        .await;
+        let swapper = match swapper {
+            Ok(swapper) => {
+                self.swappers.insert(id.clone(), swapper.control());
+                let swapper = swapper
+                    .with_paper_engine(self.paper_engine.clone())
+                    .with_position_store(self.position_store.clone())
+                    .with_journal(self.journal.clone())
+                    .with_lookup_tables(self.lookup_tables.clone());
+                match start {
+                    SwapperStart::Buy(latency) => swapper.with_latency(latency),
+                    SwapperStart::Resume(position) => swapper.resume(*position),
+                }
+            }
+            Err(e) => {
+                self.wallet_pool.release(&id);
+                return Err(e);
+            }
+        };
+
+        tracing::info!(
+            "spawned swapper with id {}, wallet {}, market id {:?}, amm id {:?}, base_mint {:?}, quote_mint {:?}",
+            id,
+            wallet.pubkey(),
+            init_pool_tx_infos.market_id,
+            init_pool_tx_infos.amm_id,
+            init_pool_tx_infos.base_mint,
+            init_pool_tx_infos.quote_mint,
+        );
+
+        if let Err(e) = ctx.spawn_deferred(id.clone(), swapper) {
+            self.wallet_pool.release(&id);
+            self.swappers.remove(&id);
+            return Err(e.into());
+        }
+        SWAPPERS_SPAWNED.inc();
+
+        Ok(())
+    }
+
+    /// Re-spawns swappers for the positions left open by a previous run, on
+    /// the wallet holding each of them
+    async fn resume_open_positions(&mut self, ctx: &mut ActorContext) {
+        let Some(position_store) = self.position_store.clone() else {
+            return;
+        };
+        let positions = match position_store.open_positions() {
+            Ok(positions) => positions,
+            Err(e) => {
+                tracing::error!("failed to load open positions: {:?}", e);
+                return;
+            }
+        };
+
+        for position in positions {
+            let id = swapper_id(&position.pool);
+            let Some(wallet) = self
+                .wallet_pool
+                .checkout_wallet(id.clone(), &position.wallet)
+            else {
+                tracing::warn!(
+                    "not resuming position in amm id {}: wallet {} is not configured",
+                    position.pool.amm_id,
+                    position.wallet
+                );
+                continue;
+            };
+
+            tracing::info!(
+                "resuming position in amm id {} after {} price checks",
+                position.pool.amm_id,
+                position.price_checks
+            );
+            let trade_amount = position.entry_lamports as f64 / *LAMPORTS_PER_SOL;
+            if let Err(e) = self
+                .spawn_swapper(
+                    ctx,
+                    id,
+                    wallet,
+                    position.pool,
+                    trade_amount,
+                    SwapperStart::Resume(Box::new(position)),
+                )
+                .await
+            {
+                tracing::error!("failed to resume position: {:?}", e);
+            }
         }
     }
 
@@ -115,11 +626,32 @@ impl Listener {
         let config = self.config.clone();
         let client = Arc::clone(&self.client);
         let self_ref: LocalActorRef<Listener> = ctx.actor_ref().clone();
+        let recorder = self.recorder.clone();
+        let health = Arc::clone(&self.health);
 
-        tokio::task::spawn(async move { listen_routine(client, self_ref, config).await });
+        tokio::task::spawn(async move {
+            listen_routine(client, self_ref, config, recorder, health).await
+        });
     }
 }
 
+/// How a spawned swapper gets its position
+enum SwapperStart {
+    /// Buy into a newly detected pool
+    Buy(LaunchLatency),
+    /// Take over a position opened before a restart
+    Resume(Box<Position>),
+}
+
+fn swapper_id(init_pool_tx_infos: &PoolInitTxInfos) -> ActorId {
+    format!(
+        "swapper-{}{}",
+        &init_pool_tx_infos.market_id.to_string()[..6],
+        &init_pool_tx_infos.amm_id.to_string()[..6],
+    )
+    .into_actor_id()
+}
+
 /// # Panic
 ///
 /// Panics if the websocket subscription fails
@@ -127,6 +659,8 @@ async fn listen_routine(
     client: Arc<RpcClient>,
     listener_reference: LocalActorRef<Listener>,
     config: ProgramConfig,
+    recorder: Option<Arc<Recorder>>,
+    health: Arc<ConnectionHealth>,
 ) {
     // Subscribes to any logs that mention the create pool fee account address.
     // Waits for the logs to reach the required commitment.
@@ -142,17 +676,29 @@ async fn listen_routine(
     )
     .await
     .expect("failed to create a ws subscription");
+    health.websocket_connected.store(true, Ordering::Relaxed);
 
     loop {
         let maybe_log = ws.read::<LogsSubscribeResponse>().await;
 
         if maybe_log.is_err() {
             tracing::debug!("failed to read: {:?}", maybe_log.err());
+            health.websocket_connected.store(false, Ordering::Relaxed);
             continue;
         }
+        health.websocket_connected.store(true, Ordering::Relaxed);
+        health.last_log_at_ms.store(now_ms(), Ordering::Relaxed);
 
         let log = maybe_log.unwrap();
-        let maybe_pool_init_tx_infos = get_pool_init_infos(Arc::clone(&client), log).await;
+        let mut latency = LaunchLatency::new(Instant::now());
+        if let Some(recorder) = &recorder {
+            recorder.record(&RecordedEvent::Log {
+                at_ms: now_ms(),
+                log: log.clone(),
+            });
+        }
+        let maybe_pool_init_tx_infos =
+            get_pool_init_infos(Arc::clone(&client), log, recorder.as_deref()).await;
         if maybe_pool_init_tx_infos.is_err() {
             tracing::debug!(
                 "error with log: {:?}",
@@ -162,8 +708,16 @@ async fn listen_routine(
         }
 
         let pool_init_tx_infos = maybe_pool_init_tx_infos.unwrap();
+        latency.stamp(Stage::TransactionFetched);
+        // Correlates the pool logs from detection to the swapper exit
+        let _span = tracing::info_span!("pool", amm_id = %pool_init_tx_infos.amm_id).entered();
+        tracing::info!("detected pool");
+        POOLS_DETECTED.inc();
+        if let Some(recorder) = &recorder {
+            recorder.track_pool(Arc::clone(&client), pool_init_tx_infos);
+        }
         let _ = listener_reference
-            .notify(SpawnSwapper(pool_init_tx_infos))
+            .notify(SpawnSwapper(pool_init_tx_infos, latency))
             .inspect_err(|err| tracing::error!("failed to spawn swapper: {:?}", err));
     }
 }
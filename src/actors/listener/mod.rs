@@ -1,2 +1,3 @@
 pub mod actor;
 pub mod utils;
+pub mod wallet_pool;
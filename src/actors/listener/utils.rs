@@ -15,21 +15,18 @@ use crate::constants::{
     AMM_ID_INDEX_IN_INIT_INSTRUCTION, AMM_V4, BASE_MINT_INDEX_IN_INIT_INSTRUCTION,
     MARKET_ID_INDEX_IN_INIT_INSTRUCTION, QUOTE_MINT_INDEX_IN_INIT_INSTRUCTION,
 };
-use crate::utils::get_transaction_from_signature;
+use crate::recording::{recorder::Recorder, RecordedEvent};
+use crate::utils::{get_transaction_from_signature, now_ms};
 use crate::websocket::LogsSubscribeResponse;
 
-/// Get the market_id and amm_id from the log response
-pub(super) async fn get_pool_init_infos(
+/// Get the market_id and amm_id from the log response, recording the fetched
+/// transaction when a recorder is given
+pub(crate) async fn get_pool_init_infos(
     client: Arc<RpcClient>,
     log: LogsSubscribeResponse,
+    recorder: Option<&Recorder>,
 ) -> Result<PoolInitTxInfos, eyre::Error> {
-    if log.params.result.value.err.is_some() {
-        return Err(eyre!("received transaction is a reverted tx"));
-    }
-
-    let signature = get_transaction_signature(log)?;
-
-    tracing::info!("Found initialize2 transaction (sig: {:?})", signature);
+    let signature = get_initialize2_signature(log)?;
 
     let pool_creation_tx = get_transaction_from_signature(
         &client,
@@ -42,8 +39,38 @@ pub(super) async fn get_pool_init_infos(
     )
     .await?;
 
-    let pool_init_tx_infos_indexes =
-        get_useful_account_indexes_from_transaction(&pool_creation_tx)?;
+    let pool_init_tx_infos = get_pool_init_infos_from_transaction(&pool_creation_tx).await;
+
+    if let Some(recorder) = recorder {
+        recorder.record(&RecordedEvent::Transaction {
+            at_ms: now_ms(),
+            signature,
+            transaction: Box::new(pool_creation_tx),
+        });
+    }
+
+    pool_init_tx_infos
+}
+
+/// Get the signature of the pool creation transaction from the log
+pub(crate) fn get_initialize2_signature(
+    log: LogsSubscribeResponse,
+) -> Result<Signature, eyre::Error> {
+    if log.params.result.value.err.is_some() {
+        return Err(eyre!("received transaction is a reverted tx"));
+    }
+
+    let signature = get_transaction_signature(log)?;
+
+    tracing::info!("Found initialize2 transaction (sig: {:?})", signature);
+    Ok(signature)
+}
+
+/// Get the market_id and amm_id from the pool creation transaction
+pub(crate) async fn get_pool_init_infos_from_transaction(
+    pool_creation_tx: &EncodedConfirmedTransactionWithStatusMeta,
+) -> Result<PoolInitTxInfos, eyre::Error> {
+    let pool_init_tx_infos_indexes = get_useful_account_indexes_from_transaction(pool_creation_tx)?;
 
     let account_keys = get_account_keys(pool_creation_tx)?;
 
@@ -64,10 +91,10 @@ pub(super) fn get_transaction_signature(
 
 /// Get the account keys from the transaction
 pub(super) fn get_account_keys(
-    tx: EncodedConfirmedTransactionWithStatusMeta,
+    tx: &EncodedConfirmedTransactionWithStatusMeta,
 ) -> Result<Vec<String>, eyre::Error> {
-    let ui_message = match tx.transaction.transaction {
-        EncodedTransaction::Json(val) => val.message,
+    let ui_message = match &tx.transaction.transaction {
+        EncodedTransaction::Json(val) => &val.message,
         _ => {
             return Err(eyre!("Unexpected format!!"));
         }
@@ -79,7 +106,7 @@ pub(super) fn get_account_keys(
             .iter()
             .map(|account_key| account_key.pubkey.to_owned())
             .collect(),
-        UiMessage::Raw(msg_raw) => msg_raw.account_keys,
+        UiMessage::Raw(msg_raw) => msg_raw.account_keys.clone(),
     };
 
     Ok(account_keys)
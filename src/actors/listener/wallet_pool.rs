@@ -0,0 +1,152 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use coerce::actor::ActorId;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use tracing::Instrument;
+
+use crate::signer::TransactionSigner;
+
+/// Wallets handed out to swappers, one per running swapper, so concurrent
+/// positions neither share a fee payer nor are trivially linkable. A pool
+/// of a single wallet, the buyer wallet when `WALLETS_DIR` isn't set, is
+/// shared by every swapper instead.
+pub struct WalletPool {
+    client: Arc<RpcClient>,
+    available: VecDeque<Arc<dyn TransactionSigner>>,
+    in_use: HashMap<ActorId, Arc<dyn TransactionSigner>>,
+    shared: bool,
+    /// Refreshed in the background, so checking a wallet out doesn't wait on
+    /// the RPC
+    balances: Arc<Mutex<HashMap<Pubkey, u64>>>,
+}
+
+impl WalletPool {
+    pub fn new(client: Arc<RpcClient>, wallets: Vec<Arc<dyn TransactionSigner>>) -> Self {
+        Self {
+            client,
+            shared: wallets.len() == 1,
+            available: wallets.into(),
+            in_use: HashMap::new(),
+            balances: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Wallets swappers can run on at the same time
+    pub fn wallet_count(&self) -> usize {
+        if self.shared {
+            1
+        } else {
+            self.available.len() + self.in_use.len()
+        }
+    }
+
+    /// Fetches the balances of every available wallet
+    pub async fn refresh_balances(&self) -> Result<(), eyre::Error> {
+        let pubkeys: Vec<Pubkey> = self
+            .available
+            .iter()
+            .map(|wallet| wallet.pubkey())
+            .collect();
+        fetch_balances(&self.client, &self.balances, &pubkeys).await
+    }
+
+    /// Assigns the first available wallet holding at least `min_lamports` to
+    /// the swapper, going by the last known balances. `min_lamports` is
+    /// deducted from the wallet balance until it is released.
+    pub fn checkout(
+        &mut self,
+        id: ActorId,
+        min_lamports: u64,
+    ) -> Option<Arc<dyn TransactionSigner>> {
+        let index = self
+            .available
+            .iter()
+            .position(|wallet| self.balance(&wallet.pubkey()) >= min_lamports)?;
+
+        let wallet = if self.shared {
+            Arc::clone(&self.available[index])
+        } else {
+            // safe to unwrap, the index was just found in the queue
+            self.available.remove(index).unwrap()
+        };
+        if let Some(balance) = self.balances.lock().unwrap().get_mut(&wallet.pubkey()) {
+            *balance = balance.saturating_sub(min_lamports);
+        }
+        self.in_use.insert(id, Arc::clone(&wallet));
+        Some(wallet)
+    }
+
+    /// Assigns a specific wallet to the swapper, to resume a position it holds
+    pub fn checkout_wallet(
+        &mut self,
+        id: ActorId,
+        pubkey: &Pubkey,
+    ) -> Option<Arc<dyn TransactionSigner>> {
+        let index = self
+            .available
+            .iter()
+            .position(|wallet| wallet.pubkey() == *pubkey)?;
+
+        let wallet = if self.shared {
+            Arc::clone(&self.available[index])
+        } else {
+            // safe to unwrap, the index was just found in the queue
+            self.available.remove(index).unwrap()
+        };
+        self.in_use.insert(id, Arc::clone(&wallet));
+        Some(wallet)
+    }
+
+    /// Returns the swapper's wallet to the back of the pool and refreshes its
+    /// balance in the background
+    pub fn release(&mut self, id: &ActorId) {
+        let Some(wallet) = self.in_use.remove(id) else {
+            return;
+        };
+        let pubkey = wallet.pubkey();
+        if !self.shared {
+            self.available.push_back(wallet);
+        }
+
+        let client = Arc::clone(&self.client);
+        let balances = Arc::clone(&self.balances);
+        tokio::spawn(
+            async move {
+                if let Err(e) = fetch_balances(&client, &balances, &[pubkey]).await {
+                    tracing::warn!("failed to refresh balance of {}: {:?}", pubkey, e);
+                }
+            }
+            .in_current_span(),
+        );
+    }
+
+    /// Last known balance of a wallet, in lamports
+    pub fn balance(&self, wallet: &Pubkey) -> u64 {
+        self.balances
+            .lock()
+            .unwrap()
+            .get(wallet)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+async fn fetch_balances(
+    client: &RpcClient,
+    balances: &Mutex<HashMap<Pubkey, u64>>,
+    pubkeys: &[Pubkey],
+) -> Result<(), eyre::Error> {
+    let accounts = client.get_multiple_accounts(pubkeys).await?;
+
+    let mut balances = balances.lock().unwrap();
+    for (pubkey, account) in pubkeys.iter().zip(accounts) {
+        let lamports = account.map(|account| account.lamports).unwrap_or_default();
+        tracing::debug!("wallet {} balance {}", pubkey, lamports);
+        balances.insert(*pubkey, lamports);
+    }
+    Ok(())
+}
@@ -1,44 +1,87 @@
-use core::time;
-use std::sync::Arc;
+use std::{str::FromStr, sync::Arc, time::Instant};
 
 use async_trait::async_trait;
 use coerce::actor::context::ActorContext;
 use coerce::actor::Actor;
-use eyre::Result;
+use eyre::{OptionExt, Result};
 use raydium_contract_instructions::amm_instruction as amm;
-use solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcSendTransactionConfig};
+use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{
-    commitment_config::{CommitmentConfig, CommitmentLevel},
-    instruction::Instruction,
-    pubkey::Pubkey,
-    signature::Keypair,
-    signer::Signer,
-    transaction::Transaction,
+    address_lookup_table::AddressLookupTableAccount, instruction::Instruction, pubkey::Pubkey,
+    signature::Signature,
 };
-use spl_associated_token_account::instruction::create_associated_token_account;
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
 
 use crate::{
-    constants::{
-        AMM_V4, LAMPORTS_PER_SOL, MAX_LIQUIDITY, MIN_LIQUIDITY, RAYDIUM_AUTHORITY_V4, SOL,
-        TOKEN_PROGRAM,
+    actors::swapper::control::{SwapperControl, SwapperSnapshot, SwapperState, TransactionStatus},
+    actors::swapper::strategy::{
+        get_position_price, Strategy, MAX_SELL_ATTEMPTS, PRICE_CHECK_INTERVAL,
     },
-    types::{MarketInfo, PoolInfo, ProgramConfig},
+    constants::{LAMPORTS_PER_SOL, RAYDIUM_AUTHORITY_V4, SOL},
+    journal::{ConfirmedSwap, Journal, TradeSide},
+    latency::{LaunchLatency, Stage},
+    metrics::{
+        DETECTION_TO_SEND_SECONDS, OPEN_POSITIONS, POOLS_FILTERED, REALIZED_PNL_SOL,
+        SEND_TO_CONFIRM_SECONDS, SWAPS_FAILED, SWAPS_LANDED, SWAPS_SENT, UNREALIZED_PNL_SOL,
+    },
+    positions::{Position, PositionStatus, PositionStore},
+    sender::{
+        build_recent_blockhash_transaction, confirm_transaction,
+        nonce::{
+            build_durable_nonce_transaction, confirm_durable_nonce_transaction, get_nonce_data,
+            lock_nonce_account,
+        },
+        paper::PaperEngine,
+        send_transaction,
+    },
+    signer::TransactionSigner,
+    types::{MarketInfo, MintInfo, PoolInfo, ProgramConfig},
     utils::{
-        get_accounts_for_swap, get_associated_authority, get_pool_and_market_info,
-        get_prio_fee_instructions, get_token_accounts,
+        get_accounts_for_swap, get_amm_id, get_associated_authority, get_epoch,
+        get_pool_and_market_info, get_prio_fee_instructions, get_swap_amount_out,
+        get_token_accounts, get_unwrap_sol_instruction, get_wrap_sol_instructions, now_ms,
     },
 };
 
 pub struct Swapper {
     client: Arc<RpcClient>,
-    user_keypair: Keypair,
+    signer: Arc<dyn TransactionSigner>,
     pool_info: PoolInfo,
     market_info: MarketInfo,
     amm_id: Pubkey,
+    base_mint_info: MintInfo,
+    quote_mint_info: MintInfo,
+    /// Epoch at startup, used to pick the active Token-2022 transfer fee
+    epoch: u64,
     user_base_token_account: Pubkey,
     user_quote_token_account: Pubkey,
     associated_authority: Pubkey,
-    account_to_create: Option<Pubkey>,
+    /// Mints whose associated token account still has to be created, pruned
+    /// once a swap created them and added back when a swap closes them
+    accounts_to_create: Vec<Pubkey>,
+    lookup_tables: Vec<AddressLookupTableAccount>,
+    nonce_account: Option<Pubkey>,
+    unwrap_sol_after_sell: bool,
+    close_token_account_after_sell: bool,
+    strategy: Strategy,
+    /// Simulated fills instead of transactions, see `--paper`
+    paper_engine: Option<Arc<PaperEngine>>,
+    /// Persists the position so it survives restarts, see `POSITIONS_DB_PATH`
+    position_store: Option<Arc<PositionStore>>,
+    /// Journals confirmed swaps, see `JOURNAL_DB_PATH`
+    journal: Option<Arc<Journal>>,
+    /// Position held by the swapper, set after the buy or when resumed
+    position: Option<Position>,
+    /// Last buy as settled on chain, the cost basis of the position
+    confirmed_buy: Option<ConfirmedSwap>,
+    /// Unrealized PnL last added to the gauge, in SOL
+    unrealized_pnl_sol: f64,
+    /// When the pool and market accounts were fetched
+    accounts_fetched_at: Instant,
+    /// Stages of the launch until the buy is confirmed
+    latency: Option<LaunchLatency>,
+    /// Shared with the listener, see `control` API
+    control: Arc<SwapperControl>,
     trade_amount: f64,
 }
 
@@ -52,33 +95,39 @@ pub struct PoolInitTxInfos {
 
 #[async_trait]
 impl Actor for Swapper {
-    #[tracing::instrument(skip_all)]
+    #[tracing::instrument(skip_all, fields(amm_id = %self.amm_id))]
     async fn started(&mut self, ctx: &mut ActorContext) {
         tracing::info!("swapper now running");
+        if let Some(latency) = &mut self.latency {
+            latency.stamp(Stage::SwapperStarted);
+        }
 
-        let (sol_vault, target_token_vault, target_token_pub_key) =
-            match (self.pool_info.base_mint, self.pool_info.quote_mint) {
-                (base, _) if *SOL == base => (
-                    self.pool_info.base_vault,
-                    self.pool_info.quote_vault,
-                    self.user_quote_token_account,
-                ),
-                (_, quote) if *SOL == quote => (
-                    self.pool_info.quote_vault,
-                    self.pool_info.base_vault,
-                    self.user_base_token_account,
-                ),
-                _ => {
-                    tracing::error!("stopping swapper: can only trade SOL");
-                    ctx.stop(None);
-                    return;
-                }
-            };
+        if self.position.is_some() {
+            tracing::info!("resuming position");
+            OPEN_POSITIONS.inc();
+            if let Err(e) = self.run_exit_strategy().await {
+                tracing::error!("stopping swapper: {:?}", e);
+            }
+            ctx.stop(None);
+            return;
+        }
+
+        let Some((sol_vault, target_token_vault, target_token_pub_key)) =
+            self.sol_and_target_accounts()
+        else {
+            tracing::error!("stopping swapper: can only trade SOL");
+            POOLS_FILTERED.with_label_values(&["not_sol_pair"]).inc();
+            self.control.filter("not_sol_pair");
+            self.report_latency();
+            ctx.stop(None);
+            return;
+        };
         tracing::info!("solana vault: {}", sol_vault);
 
         let maybe_vault_sol_account = get_token_accounts(&self.client, &[sol_vault]).await;
         if let Err(e) = maybe_vault_sol_account {
             tracing::error!("stopping swapper: failed to get token account: {:?}", e);
+            self.report_latency();
             ctx.stop(None);
             return;
         }
@@ -87,29 +136,44 @@ impl Actor for Swapper {
         // safe to unwrap, because `[get_token_accounts]` checks that returned
         // vector length matches the input vector length
         let vault_sol_token_account = vault_sol_token_account.first().unwrap();
-        if vault_sol_token_account.amount < *MIN_LIQUIDITY
-            || vault_sol_token_account.amount > *MAX_LIQUIDITY
+        if !self
+            .strategy
+            .is_liquidity_in_bounds(vault_sol_token_account.amount)
         {
             tracing::warn!(
                 "stopping swapper: liquidity not in bound to swap: {}",
                 vault_sol_token_account.amount
             );
+            POOLS_FILTERED.with_label_values(&["liquidity"]).inc();
+            self.control.filter("liquidity");
+            self.report_latency();
             ctx.stop(None);
             return;
         }
+        if let Some(latency) = &mut self.latency {
+            latency.stamp(Stage::LiquidityChecked);
+        }
 
         // BUY
         // We await here because we don't want the actor to do
         // anything else until the swap is complete.
-        if let Err(e) = self.swap(&SOL, self.trade_amount).await {
-            tracing::error!("stopping swapper: failed to swap: {:?}", e);
-            ctx.stop(None);
-            return;
-        }
+        let buy = self.swap(&SOL, self.trade_amount).await;
+        self.report_latency();
+        let buy_signature = match buy {
+            Ok(signature) => signature,
+            Err(e) => {
+                tracing::error!("stopping swapper: failed to swap: {:?}", e);
+                ctx.stop(None);
+                return;
+            }
+        };
+        self.open_position(buy_signature);
 
         // SELL
-        self.sell(target_token_pub_key, sol_vault, target_token_vault)
+        let signature = self
+            .sell(target_token_pub_key, sol_vault, target_token_vault)
             .await;
+        self.close_position(signature);
 
         // Then we can kill the swapper
         tracing::info!("stopping swapper after swap");
@@ -121,19 +185,17 @@ impl Swapper {
     pub async fn new(
         client: Arc<RpcClient>,
         config: ProgramConfig,
+        signer: Arc<dyn TransactionSigner>,
         market_id: Pubkey,
         trade_amount: f64,
     ) -> Result<Self> {
-        let amm_id = Pubkey::find_program_address(
-            &[AMM_V4.as_ref(), market_id.as_ref(), b"amm_associated_seed"],
-            &AMM_V4,
-        )
-        .0;
+        let amm_id = get_amm_id(&market_id);
         let (pool_info, _) = get_pool_and_market_info(&client, &amm_id, &market_id).await?;
 
         Swapper::from_pool_params(
             client,
             config,
+            signer,
             PoolInitTxInfos {
                 amm_id,
                 market_id,
@@ -148,85 +210,504 @@ impl Swapper {
     pub async fn from_pool_params(
         client: Arc<RpcClient>,
         config: ProgramConfig,
+        signer: Arc<dyn TransactionSigner>,
         pool_init_tx_infos: PoolInitTxInfos,
         trade_amount: f64,
     ) -> Result<Self> {
-        let user_keypair = Keypair::from_base58_string(&config.buyer_private_key);
+        let (pool_info, market_info, (base_mint_info, quote_mint_info), user_token_accounts) =
+            get_accounts_for_swap(&client, &signer.pubkey(), pool_init_tx_infos).await?;
+        let accounts_fetched_at = Instant::now();
 
-        let (pool_info, market_info, user_token_accounts) =
-            get_accounts_for_swap(&client, &user_keypair, pool_init_tx_infos).await?;
+        let epoch = get_epoch(&client).await?;
 
         let associated_authority =
             get_associated_authority(pool_info.market_program_id, pool_info.market_id).unwrap();
 
+        let nonce_account = config
+            .nonce_account_address
+            .as_deref()
+            .map(Pubkey::from_str)
+            .transpose()?;
+
+        let target_mint = if base_mint_info.mint == *SOL {
+            quote_mint_info.mint
+        } else {
+            base_mint_info.mint
+        };
+        let control = Arc::new(SwapperControl::new(SwapperSnapshot {
+            amm_id: pool_init_tx_infos.amm_id.to_string(),
+            mint: target_mint.to_string(),
+            wallet: signer.pubkey().to_string(),
+            state: SwapperState::Entering,
+            entry_lamports: None,
+            entry_price: None,
+            current_price: None,
+            unrealized_pnl_sol: None,
+            price_checks: 0,
+            opened_at_ms: None,
+            sell_requested: false,
+            filter_reason: None,
+            transactions: vec![],
+        }));
+
         Ok(Self {
             client,
-            user_keypair,
+            signer,
             pool_info,
             amm_id: pool_init_tx_infos.amm_id,
+            base_mint_info,
+            quote_mint_info,
+            epoch,
             user_base_token_account: user_token_accounts.user_base_token_account,
             user_quote_token_account: user_token_accounts.user_quote_token_account,
             market_info,
             associated_authority,
-            account_to_create: user_token_accounts.account_to_create,
+            accounts_to_create: user_token_accounts.accounts_to_create,
+            lookup_tables: vec![],
+            nonce_account,
+            unwrap_sol_after_sell: config.unwrap_sol_after_sell,
+            close_token_account_after_sell: config.close_token_account_after_sell,
+            strategy: Strategy::default(),
+            paper_engine: None,
+            position_store: None,
+            journal: None,
+            position: None,
+            confirmed_buy: None,
+            unrealized_pnl_sol: 0.,
+            accounts_fetched_at,
+            latency: None,
+            control,
             trade_amount,
         })
     }
 
+    /// Fills swaps with the paper engine instead of sending transactions
+    pub fn with_paper_engine(mut self, paper_engine: Option<Arc<PaperEngine>>) -> Self {
+        self.paper_engine = paper_engine;
+        self
+    }
+
+    /// Persists the position to `position_store`
+    pub fn with_position_store(mut self, position_store: Option<Arc<PositionStore>>) -> Self {
+        self.position_store = position_store;
+        self
+    }
+
+    /// Resolves accounts through `lookup_tables`, loaded once at startup
+    pub fn with_lookup_tables(mut self, lookup_tables: Vec<AddressLookupTableAccount>) -> Self {
+        self.lookup_tables = lookup_tables;
+        self
+    }
+
+    /// Journals the swaps confirmed on chain to `journal`
+    pub fn with_journal(mut self, journal: Option<Arc<Journal>>) -> Self {
+        self.journal = journal;
+        self
+    }
+
+    /// Times the launch stages until the buy is confirmed
+    pub fn with_latency(mut self, mut latency: LaunchLatency) -> Self {
+        latency.stamp_at(Stage::AccountsFetched, self.accounts_fetched_at);
+        self.latency = Some(latency);
+        self
+    }
+
+    /// Skips the buy and runs the exit strategy on a position opened before
+    /// a restart, picking up from its last price check
+    pub fn resume(mut self, position: Position) -> Self {
+        self.update_control_position(&position);
+        self.position = Some(position);
+        self
+    }
+
+    /// State shared with the listener
+    pub fn control(&self) -> Arc<SwapperControl> {
+        Arc::clone(&self.control)
+    }
+
+    fn update_control_position(&self, position: &Position) {
+        self.control.update(|snapshot| {
+            snapshot.state = SwapperState::Holding;
+            snapshot.entry_lamports = Some(position.entry_lamports);
+            snapshot.entry_price = position.entry_price;
+            snapshot.price_checks = position.price_checks;
+            snapshot.opened_at_ms = Some(position.opened_at_ms);
+        });
+    }
+
+    /// Opens the position at the exact SOL spent and tokens received by the
+    /// confirmed buy. Without it, e.g. in paper mode, the entry price is
+    /// estimated from the trade amount and the balance at the first check.
+    fn open_position(&mut self, buy_signature: Signature) {
+        let confirmed_buy = self.confirmed_buy.filter(|buy| buy.tokens > 0);
+        let position = Position {
+            pool: PoolInitTxInfos {
+                amm_id: self.amm_id,
+                market_id: self.pool_info.market_id,
+                base_mint: self.pool_info.base_mint,
+                quote_mint: self.pool_info.quote_mint,
+            },
+            mint: self.target_mint_info().mint,
+            wallet: self.signer.pubkey(),
+            entry_lamports: confirmed_buy
+                .map(|buy| buy.lamports)
+                .unwrap_or_else(|| self.to_base_units(&SOL, self.trade_amount)),
+            entry_tokens: confirmed_buy.map(|buy| buy.tokens),
+            entry_price: confirmed_buy.map(|buy| buy.lamports as f64 / buy.tokens as f64),
+            buy_signature,
+            sell_signature: None,
+            price_checks: 0,
+            status: PositionStatus::Open,
+            opened_at_ms: now_ms(),
+            closed_at_ms: None,
+        };
+        if let Some(position_store) = &self.position_store {
+            if let Err(e) = position_store.insert(&position) {
+                tracing::error!("failed to persist position: {:?}", e);
+            }
+        }
+        self.update_control_position(&position);
+        self.position = Some(position);
+        OPEN_POSITIONS.inc();
+    }
+
+    fn close_position(&mut self, sell_signature: Option<Signature>) {
+        let status = match sell_signature {
+            Some(_) => PositionStatus::Sold,
+            None => PositionStatus::Expired,
+        };
+        if self.position.is_some() {
+            OPEN_POSITIONS.dec();
+        }
+        self.set_unrealized_pnl(0.);
+        self.control
+            .update(|snapshot| snapshot.state = SwapperState::Exited);
+        if let Some(position) = &mut self.position {
+            position.status = status;
+            position.sell_signature = sell_signature;
+            position.closed_at_ms = Some(now_ms());
+        }
+        if let Some(position_store) = &self.position_store {
+            if let Err(e) = position_store.close(&self.amm_id, status, sell_signature) {
+                tracing::error!("failed to persist position: {:?}", e);
+            }
+        }
+    }
+
+    /// Hands the position over to the exit strategy, returning the sell
+    /// signature or `None` when it gave up without selling
+    pub async fn run_exit_strategy(&mut self) -> Result<Option<Signature>> {
+        let (sol_vault, target_token_vault, target_token_pub_key) = self
+            .sol_and_target_accounts()
+            .ok_or_eyre("can only trade SOL")?;
+        let signature = self
+            .sell(target_token_pub_key, sol_vault, target_token_vault)
+            .await;
+        self.close_position(signature);
+        Ok(signature)
+    }
+
+    /// SOL vault, target token vault and user target token account of a
+    /// pool traded against SOL
+    fn sol_and_target_accounts(&self) -> Option<(Pubkey, Pubkey, Pubkey)> {
+        match (self.pool_info.base_mint, self.pool_info.quote_mint) {
+            (base, _) if *SOL == base => Some((
+                self.pool_info.base_vault,
+                self.pool_info.quote_vault,
+                self.user_quote_token_account,
+            )),
+            (_, quote) if *SOL == quote => Some((
+                self.pool_info.quote_vault,
+                self.pool_info.base_vault,
+                self.user_base_token_account,
+            )),
+            _ => None,
+        }
+    }
+
     pub async fn sell(
-        &self,
+        &mut self,
         target_token_pub_key: Pubkey,
         sol_vault_pub_key: Pubkey,
         target_token_vault_pub_key: Pubkey,
-    ) {
-        let mut i = 0;
+    ) -> Option<Signature> {
+        let mut i = self
+            .position
+            .as_ref()
+            .map(|position| position.price_checks)
+            .unwrap_or_default();
+        let mut failed_sells = 0;
         loop {
-            tokio::time::sleep(time::Duration::from_secs(3)).await;
-            let maybe_token_accounts = get_token_accounts(
-                &self.client,
-                &[
+            tokio::time::sleep(PRICE_CHECK_INTERVAL).await;
+            let target_mint = self.target_mint_info().mint;
+            let maybe_token_amounts = self
+                .get_user_and_token_amounts(
+                    &target_mint,
                     target_token_pub_key,
-                    sol_vault_pub_key,
-                    target_token_vault_pub_key,
-                ],
-            )
-            .await;
+                    &[sol_vault_pub_key, target_token_vault_pub_key],
+                )
+                .await;
 
-            if let Err(e) = maybe_token_accounts {
+            if let Err(e) = maybe_token_amounts {
                 tracing::error!("failed to get token accounts: {:?}", e);
                 continue;
             }
 
-            let token_accounts = maybe_token_accounts.unwrap();
-            // safe to unwrap, because `[get_token_accounts]` checks that returned
-            // vector length matches the input vector length
-            let target_token_amount = token_accounts.first().unwrap().amount as f64;
-            let sol_vault_amount = token_accounts.get(1).unwrap().amount as f64;
-            let target_token_vault_amount = token_accounts.get(2).unwrap().amount as f64;
+            let token_amounts = maybe_token_amounts.unwrap();
+            let target_token_amount = token_amounts[0];
+            if target_token_amount == 0 {
+                tracing::warn!("stopping exit strategy: no tokens left to sell");
+                return None;
+            }
+
+            // Token-2022 transfer fees are withheld from the tokens sent to the pool
+            let sell_transfer_fee = self
+                .target_mint_info()
+                .transfer_fee(target_token_amount, self.epoch);
 
-            let buy_price = (self.trade_amount * *LAMPORTS_PER_SOL) / target_token_amount;
-            let current_price = sol_vault_amount / target_token_vault_amount;
+            let buy_price = match self.entry_price() {
+                Some(entry_price) => entry_price,
+                None => (self.trade_amount * *LAMPORTS_PER_SOL) / target_token_amount as f64,
+            };
+            let current_price = get_position_price(
+                target_token_amount,
+                sell_transfer_fee,
+                token_amounts[1],
+                token_amounts[2],
+            );
 
             tracing::debug!("buy price: {} current price: {}", buy_price, current_price);
+            self.record_price_check(i, target_token_amount, buy_price);
+            let unrealized_pnl_sol =
+                (current_price - buy_price) * target_token_amount as f64 / *LAMPORTS_PER_SOL;
+            self.set_unrealized_pnl(unrealized_pnl_sol);
+            self.control.update(|snapshot| {
+                snapshot.entry_price = Some(buy_price);
+                snapshot.current_price = Some(current_price);
+                snapshot.unrealized_pnl_sol = Some(unrealized_pnl_sol);
+                snapshot.price_checks = i;
+            });
 
-            if current_price > 2. * buy_price {
-                tracing::info!("selling");
-                if let Err(e) = self.swap(&target_token_pub_key, target_token_amount).await {
-                    tracing::error!("failed to swap: {:?}", e);
-                    continue;
+            let sell_requested = self.control.is_sell_requested();
+            if sell_requested || self.strategy.should_take_profit(buy_price, current_price) {
+                tracing::info!(sell_requested, "selling");
+                match self
+                    .swap_raw(
+                        &target_mint,
+                        target_token_amount,
+                        0,
+                        self.close_token_account_after_sell,
+                    )
+                    .await
+                {
+                    Ok(signature) => return Some(signature),
+                    Err(e) => {
+                        tracing::error!("failed to swap: {:?}", e);
+                        failed_sells += 1;
+                        if failed_sells >= MAX_SELL_ATTEMPTS {
+                            tracing::error!(
+                                "stopping exit strategy after {} failed sells",
+                                failed_sells
+                            );
+                            return None;
+                        }
+                        continue;
+                    }
                 }
-                break;
             }
 
-            if i > 100 {
-                tracing::info!("stopping swapper after 100 iterations");
-                break;
+            if !sell_requested && self.strategy.is_expired(i) {
+                tracing::info!("stopping swapper after {} iterations", i);
+                return None;
             }
             i += 1;
         }
     }
 
-    pub async fn swap(&self, in_token: &Pubkey, amount_in: f64) -> Result<()> {
+    /// Lamports paid per base unit of the target token, from the position or
+    /// else the last confirmed buy
+    fn entry_price(&self) -> Option<f64> {
+        self.position
+            .as_ref()
+            .and_then(|position| position.entry_price)
+            .or_else(|| {
+                self.confirmed_buy
+                    .filter(|buy| buy.tokens > 0)
+                    .map(|buy| buy.lamports as f64 / buy.tokens as f64)
+            })
+    }
+
+    /// Emits the launch stages reached so far, once
+    fn report_latency(&mut self) {
+        if let Some(latency) = self.latency.take() {
+            latency.report(&self.amm_id, self.confirmed_buy.map(|buy| buy.slot));
+        }
+    }
+
+    /// Moves this position's share of the unrealized PnL gauge
+    fn set_unrealized_pnl(&mut self, unrealized_pnl_sol: f64) {
+        UNREALIZED_PNL_SOL.add(unrealized_pnl_sol - self.unrealized_pnl_sol);
+        self.unrealized_pnl_sol = unrealized_pnl_sol;
+    }
+
+    /// Adds the PnL of selling the position for `sell_lamports`, net of the
+    /// sell fees, to the realized PnL gauge
+    fn record_realized_pnl(&self, sell_lamports: u64) {
+        let Some(position) = &self.position else {
+            return;
+        };
+        let cost_lamports = match self.confirmed_buy {
+            Some(buy) => buy.lamports + buy.fee_lamports(),
+            None => position.entry_lamports,
+        };
+        let realized_pnl_sol = (sell_lamports as f64 - cost_lamports as f64) / *LAMPORTS_PER_SOL;
+        tracing::info!("realized pnl {} SOL", realized_pnl_sol);
+        REALIZED_PNL_SOL.add(realized_pnl_sol);
+    }
+
+    fn record_price_check(&mut self, price_checks: u32, entry_tokens: u64, entry_price: f64) {
+        let Some(position) = &mut self.position else {
+            return;
+        };
+        position.price_checks = price_checks;
+        position.entry_tokens.get_or_insert(entry_tokens);
+        position.entry_price.get_or_insert(entry_price);
+        if let Some(position_store) = &self.position_store {
+            if let Err(e) = position_store.record_price_check(
+                &self.amm_id,
+                price_checks,
+                entry_tokens,
+                entry_price,
+            ) {
+                tracing::error!("failed to persist position: {:?}", e);
+            }
+        }
+    }
+
+    pub async fn swap(&mut self, in_token: &Pubkey, amount_in: f64) -> Result<Signature> {
+        let amount_in = self.to_base_units(in_token, amount_in);
+        self.swap_raw(in_token, amount_in, 0, false).await
+    }
+
+    /// Converts an amount in decimals of one of the pool tokens to base units
+    pub fn to_base_units(&self, token: &Pubkey, amount: f64) -> u64 {
+        let decimals = if self.pool_info.base_mint == *token {
+            self.pool_info.base_decimal
+        } else {
+            self.pool_info.quote_decimal
+        };
+        (amount * 10_f64.powi(decimals.try_into().unwrap())) as u64
+    }
+
+    /// Sells the whole target token balance for at least the pool quote minus
+    /// `slippage_bps`, returning the amount sold and the transaction signature
+    pub async fn sell_all(&mut self, slippage_bps: u64) -> Result<(u64, Signature)> {
+        let target_mint = self.target_mint_info().mint;
+        if target_mint == *SOL {
+            return Err(eyre::eyre!("can only sell tokens traded against SOL"));
+        }
+        self.swap_pct(&target_mint, 100., slippage_bps).await
+    }
+
+    /// Swaps `pct` percent of the `in_token` balance for at least the pool
+    /// quote minus `slippage_bps`, returning the amount swapped and the
+    /// transaction signature
+    pub async fn swap_pct(
+        &mut self,
+        in_token: &Pubkey,
+        pct: f64,
+        slippage_bps: u64,
+    ) -> Result<(u64, Signature)> {
+        let base_in = self.pool_info.base_mint == *in_token;
+        let user_in_token_account = if base_in {
+            self.user_base_token_account
+        } else {
+            self.user_quote_token_account
+        };
+
+        let token_amounts = self
+            .get_user_and_token_amounts(
+                in_token,
+                user_in_token_account,
+                &[self.pool_info.base_vault, self.pool_info.quote_vault],
+            )
+            .await?;
+        let balance = token_amounts[0];
+        let is_full_balance = pct >= 100.;
+        let amount_in = if is_full_balance {
+            balance
+        } else {
+            (balance as u128 * (pct.max(0.) * 100.) as u128 / 10_000) as u64
+        };
+        if amount_in == 0 {
+            return Err(eyre::eyre!("no {} to swap", in_token));
+        }
+
+        // Token-2022 transfer fees are withheld from the tokens sent to the pool
+        let transfer_fee = self.mint_info(in_token).transfer_fee(amount_in, self.epoch);
+        let amount_out = get_swap_amount_out(
+            &self.pool_info,
+            token_amounts[1],
+            token_amounts[2],
+            amount_in - transfer_fee,
+            base_in,
+        );
+        let minimum_amount_out =
+            (amount_out as u128 * 10_000_u64.saturating_sub(slippage_bps) as u128 / 10_000) as u64;
+        tracing::info!(
+            "swapping {} of {} for minimum {} out",
+            amount_in,
+            in_token,
+            minimum_amount_out
+        );
+
+        let signature = self
+            .swap_raw(
+                in_token,
+                amount_in,
+                minimum_amount_out,
+                is_full_balance && self.close_token_account_after_sell,
+            )
+            .await?;
+        Ok((amount_in, signature))
+    }
+
+    /// Swaps `amount_in` base units of `in_token`. When `close_in_token_account`
+    /// is set the whole balance must be swapped, the emptied token account is
+    /// closed in the same transaction and its rent returned to the signer.
+    async fn swap_raw(
+        &mut self,
+        in_token: &Pubkey,
+        amount_in: u64,
+        minimum_amount_out: u64,
+        close_in_token_account: bool,
+    ) -> Result<Signature> {
+        if let Some(paper_engine) = self.paper_engine.clone() {
+            let out_token = if *in_token == self.pool_info.base_mint {
+                self.pool_info.quote_mint
+            } else {
+                self.pool_info.base_mint
+            };
+            let (amount_out, signature) = paper_engine
+                .fill(
+                    &self.client,
+                    &self.pool_info,
+                    (self.mint_info(in_token), self.mint_info(&out_token)),
+                    self.epoch,
+                    amount_in,
+                    minimum_amount_out,
+                )
+                .await?;
+            let is_sell = *in_token != *SOL;
+            let confirmed_swap = if is_sell {
+                ConfirmedSwap::paper(amount_out, amount_in)
+            } else {
+                ConfirmedSwap::paper(amount_in, amount_out)
+            };
+            self.settle_swap(&signature, is_sell, confirmed_swap);
+            return Ok(signature);
+        }
+
         let mut instructions = vec![];
         let (user_out_token_account, user_in_token_account) =
             if *in_token == self.pool_info.base_mint {
@@ -240,37 +721,205 @@ impl Swapper {
         instructions.push(compute_unit_limit_instruction);
         instructions.push(compute_unit_price_instruction);
 
-        if self.account_to_create.is_some() {
-            let associated_token_account_create_instruction = create_associated_token_account(
-                &self.user_keypair.pubkey(),
-                &self.user_keypair.pubkey(),
-                &self.account_to_create.unwrap(),
-                &TOKEN_PROGRAM,
-            );
-            instructions.push(associated_token_account_create_instruction);
-        }
-
-        let amount_in = if self.pool_info.base_mint == *in_token {
-            amount_in * 10_f64.powi(self.pool_info.base_decimal.try_into().unwrap())
+        let is_sell = *in_token != *SOL;
+        let user_wsol_token_account = if self.pool_info.base_mint == *SOL {
+            self.user_base_token_account
         } else {
-            amount_in * 10_f64.powi(self.pool_info.quote_decimal.try_into().unwrap())
+            self.user_quote_token_account
         };
-        tracing::debug!("swap base in: {} for minimum 0 out", amount_in);
+
+        // The WSOL account is created by the wrap instructions on buys. The
+        // token account is always created, idempotently, since signing
+        // policies only let swaps reach the token accounts they can prove
+        // the signer owns.
+        let token_mint = self.target_mint_info().mint;
+        let mut mints_to_create: Vec<Pubkey> = self
+            .accounts_to_create
+            .iter()
+            .copied()
+            .filter(|mint| is_sell || *mint != *SOL)
+            .collect();
+        if !mints_to_create.contains(&token_mint) {
+            mints_to_create.push(token_mint);
+        }
+        for mint in &mints_to_create {
+            instructions.push(create_associated_token_account_idempotent(
+                &self.signer.pubkey(),
+                &self.signer.pubkey(),
+                mint,
+                &self.mint_info(mint).token_program,
+            ));
+        }
+
+        if !is_sell {
+            instructions.extend(get_wrap_sol_instructions(
+                &self.signer.pubkey(),
+                &user_wsol_token_account,
+                amount_in,
+            )?);
+        }
+
+        tracing::debug!(
+            "swap base in: {} for minimum {} out",
+            amount_in,
+            minimum_amount_out
+        );
         let instruction = self.build_swap_base_in_instruction(
             amount_in,
-            0.,
+            minimum_amount_out,
             user_in_token_account,
             user_out_token_account,
         );
 
         instructions.push(instruction);
-        self.sign_and_send_instructions(instructions).await
+
+        let unwrap_sol = is_sell && self.unwrap_sol_after_sell;
+        if unwrap_sol {
+            instructions.push(get_unwrap_sol_instruction(
+                &self.signer.pubkey(),
+                &user_wsol_token_account,
+            )?);
+        }
+
+        // The WSOL account is closed by the unwrap instruction already
+        let close_in_token_account = close_in_token_account && *in_token != *SOL;
+        if close_in_token_account {
+            instructions.push(spl_token_2022::instruction::close_account(
+                &self.mint_info(in_token).token_program,
+                &user_in_token_account,
+                &self.signer.pubkey(),
+                &self.signer.pubkey(),
+                &[],
+            )?);
+        }
+
+        let signature = self
+            .sign_and_send_instructions(instructions, is_sell)
+            .await?;
+        self.confirm_swap(&signature, is_sell).await;
+
+        self.accounts_to_create.clear();
+        // The WSOL account is gone once unwrapped, following sells need it again
+        if unwrap_sol {
+            self.accounts_to_create.push(*SOL);
+        }
+        if close_in_token_account {
+            self.accounts_to_create.push(*in_token);
+        }
+        Ok(signature)
+    }
+
+    /// Reads the swap back from its confirmed transaction to settle it.
+    /// Errors are only logged since the swap went through.
+    async fn confirm_swap(&mut self, signature: &Signature, is_sell: bool) {
+        if self.sol_and_target_accounts().is_none() {
+            return;
+        }
+        let mint = self.target_mint_info().mint;
+        let confirmed_swap = match ConfirmedSwap::fetch(
+            &self.client,
+            *signature,
+            &self.signer.pubkey(),
+            &mint,
+        )
+        .await
+        {
+            Ok(confirmed_swap) => confirmed_swap,
+            Err(e) => {
+                tracing::error!("failed to read swap {}: {:?}", signature, e);
+                return;
+            }
+        };
+        self.settle_swap(signature, is_sell, confirmed_swap);
+    }
+
+    /// Keeps the cost basis of a buy or realizes the PnL of a sell, and
+    /// journals the swap, filled on chain or by the paper engine alike
+    fn settle_swap(&mut self, signature: &Signature, is_sell: bool, confirmed_swap: ConfirmedSwap) {
+        if is_sell {
+            self.record_realized_pnl(
+                confirmed_swap
+                    .lamports
+                    .saturating_sub(confirmed_swap.fee_lamports()),
+            );
+        } else {
+            self.confirmed_buy = Some(confirmed_swap);
+        }
+
+        let Some(journal) = &self.journal else {
+            return;
+        };
+        let mint = self.target_mint_info().mint;
+        let side = if is_sell {
+            TradeSide::Sell
+        } else {
+            TradeSide::Buy
+        };
+        match journal.append(
+            side,
+            &self.signer.pubkey(),
+            &self.amm_id,
+            &mint,
+            signature,
+            &confirmed_swap,
+        ) {
+            Ok(entry) => tracing::info!(
+                "journaled {:?} of {} in, {} out, realized pnl {:?} lamports",
+                side,
+                entry.amount_in,
+                entry.amount_out,
+                entry.realized_pnl_lamports
+            ),
+            Err(e) => tracing::error!("failed to journal swap {}: {:?}", signature, e),
+        }
+    }
+
+    /// Balance of the user's `mint` token account followed by the amounts
+    /// of `token_accounts`. The balance comes from the paper book in paper mode.
+    async fn get_user_and_token_amounts(
+        &self,
+        mint: &Pubkey,
+        user_token_account: Pubkey,
+        token_accounts: &[Pubkey],
+    ) -> Result<Vec<u64>> {
+        let mut accounts = vec![];
+        if self.paper_engine.is_none() {
+            accounts.push(user_token_account);
+        }
+        accounts.extend_from_slice(token_accounts);
+
+        let mut amounts: Vec<u64> = get_token_accounts(&self.client, &accounts)
+            .await?
+            .iter()
+            .map(|token_account| token_account.amount)
+            .collect();
+        if let Some(paper_engine) = &self.paper_engine {
+            amounts.insert(0, paper_engine.balance(mint));
+        }
+        Ok(amounts)
+    }
+
+    fn mint_info(&self, mint: &Pubkey) -> &MintInfo {
+        if *mint == self.base_mint_info.mint {
+            &self.base_mint_info
+        } else {
+            &self.quote_mint_info
+        }
+    }
+
+    /// Mint info of the token traded against SOL
+    fn target_mint_info(&self) -> &MintInfo {
+        if self.base_mint_info.mint == *SOL {
+            &self.quote_mint_info
+        } else {
+            &self.base_mint_info
+        }
     }
 
     fn build_swap_base_in_instruction(
         &self,
-        amount_in: f64,
-        amount_out: f64,
+        amount_in: u64,
+        amount_out: u64,
         user_in_token_account: Pubkey,
         user_out_token_account: Pubkey,
     ) -> Instruction {
@@ -292,42 +941,117 @@ impl Swapper {
             &self.associated_authority,
             &user_in_token_account,
             &user_out_token_account,
-            &self.user_keypair.pubkey(),
-            amount_in as u64,
-            amount_out as u64,
+            &self.signer.pubkey(),
+            amount_in,
+            amount_out,
         )
         .unwrap()
     }
 
-    async fn sign_and_send_instructions(&self, instructions: Vec<Instruction>) -> Result<()> {
-        let recent_blockhash = self
-            .client
-            .get_latest_blockhash_with_commitment(solana_sdk::commitment_config::CommitmentConfig {
-                commitment: solana_sdk::commitment_config::CommitmentLevel::Finalized,
-            })
-            .await
-            .unwrap()
-            .0;
-
-        let transaction = Transaction::new_signed_with_payer(
-            &instructions,
-            Some(&self.user_keypair.pubkey()),
-            &vec![&self.user_keypair],
-            recent_blockhash,
-        );
+    /// Sells are built against the durable nonce when a nonce account is
+    /// configured and the wallet is its authority, everything else uses a
+    /// recent blockhash. The nonce account stays locked until the sell is
+    /// confirmed, so concurrent sells don't reuse its value.
+    async fn sign_and_send_instructions(
+        &mut self,
+        instructions: Vec<Instruction>,
+        is_sell: bool,
+    ) -> Result<Signature> {
+        let mut nonce_guard = None;
+        let mut transaction = None;
+        if let Some(nonce_account) = self.nonce_account.filter(|_| is_sell) {
+            let guard = lock_nonce_account(&nonce_account).await;
+            let nonce_data = get_nonce_data(&self.client, &nonce_account).await?;
+            if nonce_data.authority == self.signer.pubkey() {
+                transaction = Some(
+                    build_durable_nonce_transaction(
+                        self.signer.as_ref(),
+                        &nonce_account,
+                        &nonce_data,
+                        &instructions,
+                        &self.lookup_tables,
+                    )
+                    .await?,
+                );
+                nonce_guard = Some((guard, nonce_account, nonce_data.blockhash()));
+            } else {
+                // Pooled wallets share NONCE_ACCOUNT_ADDRESS, only its
+                // authority can advance it
+                tracing::debug!(
+                    "{} is not the authority of nonce account {}, selling with a recent blockhash",
+                    self.signer.pubkey(),
+                    nonce_account
+                );
+                self.nonce_account = None;
+            }
+        }
+        let transaction = match transaction {
+            Some(transaction) => transaction,
+            None => {
+                build_recent_blockhash_transaction(
+                    &self.client,
+                    self.signer.as_ref(),
+                    &instructions,
+                    &self.lookup_tables,
+                )
+                .await?
+            }
+        };
 
-        self.client
-            .send_and_confirm_transaction_with_spinner_and_config(
-                &transaction,
-                CommitmentConfig::confirmed(),
-                RpcSendTransactionConfig {
-                    skip_preflight: true,
-                    preflight_commitment: Some(CommitmentLevel::Processed),
-                    ..RpcSendTransactionConfig::default()
-                },
-            )
-            .await
-            .inspect_err(|e| tracing::error!("failed to send transaction: {:?}", e))?;
-        Ok(())
+        let side = if is_sell {
+            TradeSide::Sell
+        } else {
+            TradeSide::Buy
+        };
+        let signature = transaction.signatures[0].to_string();
+        self.control
+            .record_transaction(side, signature.clone(), TransactionStatus::Pending);
+        // Only the buy of a detected pool is part of its launch
+        let mut latency = self.latency.as_mut().filter(|_| !is_sell);
+        if let Some(latency) = &mut latency {
+            latency.stamp(Stage::TransactionSigned);
+            DETECTION_TO_SEND_SECONDS.observe(latency.log_received().elapsed().as_secs_f64());
+        }
+        SWAPS_SENT.with_label_values(&[side.as_str()]).inc();
+        let result = match send_transaction(&self.client, &transaction).await {
+            Ok(signature) => {
+                let sent_at = Instant::now();
+                if let Some(latency) = &mut latency {
+                    latency.stamp_at(Stage::TransactionSent, sent_at);
+                }
+                let result = match &nonce_guard {
+                    Some((_, nonce_account, nonce_blockhash)) => {
+                        confirm_durable_nonce_transaction(
+                            &self.client,
+                            self.signer.as_ref(),
+                            &signature,
+                            nonce_account,
+                            nonce_blockhash,
+                        )
+                        .await
+                    }
+                    None => confirm_transaction(&self.client, &signature).await,
+                };
+                if result.is_ok() {
+                    SEND_TO_CONFIRM_SECONDS.observe(sent_at.elapsed().as_secs_f64());
+                    if let Some(latency) = &mut latency {
+                        latency.stamp(Stage::TransactionConfirmed);
+                    }
+                }
+                result.map(|_| signature)
+            }
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(_) => SWAPS_LANDED.with_label_values(&[side.as_str()]).inc(),
+            Err(_) => SWAPS_FAILED.with_label_values(&[side.as_str()]).inc(),
+        }
+        let status = match result {
+            Ok(_) => TransactionStatus::Landed,
+            Err(_) => TransactionStatus::Failed,
+        };
+        self.control.record_transaction(side, signature, status);
+        drop(nonce_guard);
+        result
     }
 }
@@ -0,0 +1,138 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+use serde::Serialize;
+
+use crate::{journal::TradeSide, utils::now_ms};
+
+/// Transactions kept per swapper, oldest are dropped first
+const MAX_TRANSACTIONS: usize = 10;
+
+/// State of a running swapper shared with the listener. The swapper runs its
+/// whole trade in `started`, so it cannot handle messages of its own.
+#[derive(Debug)]
+pub struct SwapperControl {
+    snapshot: Mutex<SwapperSnapshot>,
+    sell_requested: AtomicBool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapperState {
+    /// Checking the pool and buying
+    Entering,
+    /// Running the exit strategy
+    Holding,
+    /// Sold or given up on the position
+    Exited,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionStatus {
+    Pending,
+    Landed,
+    Failed,
+}
+
+/// Swap transaction sent by a swapper
+#[derive(Debug, Clone, Serialize)]
+pub struct SwapperTransaction {
+    pub at_ms: u64,
+    pub amm_id: String,
+    pub side: TradeSide,
+    pub signature: String,
+    pub status: TransactionStatus,
+}
+
+/// What a swapper is doing, as of its last price check
+#[derive(Debug, Clone, Serialize)]
+pub struct SwapperSnapshot {
+    pub amm_id: String,
+    /// Token traded against SOL
+    pub mint: String,
+    pub wallet: String,
+    pub state: SwapperState,
+    /// SOL spent on the buy, in lamports
+    pub entry_lamports: Option<u64>,
+    /// Lamports paid per base unit of the token
+    pub entry_price: Option<f64>,
+    /// Lamports per base unit of the token at the last price check
+    pub current_price: Option<f64>,
+    pub unrealized_pnl_sol: Option<f64>,
+    pub price_checks: u32,
+    pub opened_at_ms: Option<u64>,
+    pub sell_requested: bool,
+    /// Why the pool was not traded, when the swapper exited before buying
+    pub filter_reason: Option<&'static str>,
+    /// Latest transactions first
+    pub transactions: Vec<SwapperTransaction>,
+}
+
+impl SwapperControl {
+    pub fn new(snapshot: SwapperSnapshot) -> Self {
+        Self {
+            snapshot: Mutex::new(snapshot),
+            sell_requested: AtomicBool::new(false),
+        }
+    }
+
+    pub fn snapshot(&self) -> SwapperSnapshot {
+        let mut snapshot = self.snapshot.lock().unwrap().clone();
+        snapshot.sell_requested = self.is_sell_requested();
+        snapshot
+    }
+
+    pub fn update(&self, update: impl FnOnce(&mut SwapperSnapshot)) {
+        update(&mut self.snapshot.lock().unwrap());
+    }
+
+    /// Records a sent transaction, or updates its status once confirmed
+    pub fn record_transaction(
+        &self,
+        side: TradeSide,
+        signature: String,
+        status: TransactionStatus,
+    ) {
+        self.update(|snapshot| {
+            if let Some(transaction) = snapshot
+                .transactions
+                .iter_mut()
+                .find(|transaction| transaction.signature == signature)
+            {
+                transaction.status = status;
+                return;
+            }
+            snapshot.transactions.insert(
+                0,
+                SwapperTransaction {
+                    at_ms: now_ms(),
+                    amm_id: snapshot.amm_id.clone(),
+                    side,
+                    signature,
+                    status,
+                },
+            );
+            snapshot.transactions.truncate(MAX_TRANSACTIONS);
+        });
+    }
+
+    /// Marks the swapper as exited without trading the pool
+    pub fn filter(&self, reason: &'static str) {
+        self.update(|snapshot| {
+            snapshot.state = SwapperState::Exited;
+            snapshot.filter_reason = Some(reason);
+        });
+    }
+
+    /// Has the exit strategy sell at its next price check, whatever the price
+    pub fn request_sell(&self) {
+        self.sell_requested.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_sell_requested(&self) -> bool {
+        self.sell_requested.load(Ordering::Relaxed)
+    }
+}
@@ -1 +1,3 @@
 pub mod actor;
+pub mod control;
+pub mod strategy;
@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+use crate::constants::{MAX_LIQUIDITY, MIN_LIQUIDITY};
+
+/// Time between two price checks of an open position
+pub const PRICE_CHECK_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Failed sells after which the position is given up on
+pub const MAX_SELL_ATTEMPTS: u32 = 5;
+
+/// Entry filter and exit rules applied by a swapper, shared with replays so
+/// both take the same decisions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Strategy {
+    /// Pools whose SOL vault holds fewer lamports are skipped
+    pub min_liquidity: u64,
+    /// Pools whose SOL vault holds more lamports are skipped
+    pub max_liquidity: u64,
+    /// Sell once the price reaches this multiple of the buy price
+    pub take_profit_multiple: f64,
+    /// Price checks after which the position is given up on
+    pub max_price_checks: u32,
+}
+
+impl Default for Strategy {
+    fn default() -> Self {
+        Self {
+            min_liquidity: *MIN_LIQUIDITY,
+            max_liquidity: *MAX_LIQUIDITY,
+            take_profit_multiple: 2.,
+            max_price_checks: 100,
+        }
+    }
+}
+
+impl Strategy {
+    pub fn is_liquidity_in_bounds(&self, sol_vault_amount: u64) -> bool {
+        (self.min_liquidity..=self.max_liquidity).contains(&sol_vault_amount)
+    }
+
+    pub fn should_take_profit(&self, buy_price: f64, current_price: f64) -> bool {
+        current_price > self.take_profit_multiple * buy_price
+    }
+
+    /// Whether the position is given up on after the `check`th price check,
+    /// counted from zero
+    pub fn is_expired(&self, check: u32) -> bool {
+        check > self.max_price_checks
+    }
+}
+
+/// Price of one base unit of the position in lamports, net of the Token-2022
+/// transfer fee withheld when selling it, at the pool spot price
+pub fn get_position_price(
+    target_token_amount: u64,
+    sell_transfer_fee: u64,
+    sol_vault_amount: u64,
+    target_token_vault_amount: u64,
+) -> f64 {
+    let target_token_amount = target_token_amount as f64;
+    (sol_vault_amount as f64 / target_token_vault_amount as f64)
+        * (target_token_amount - sell_transfer_fee as f64)
+        / target_token_amount
+}
@@ -20,12 +20,26 @@ lazy_static! {
         Pubkey::from_str("5Q544fKrFoe6tsEbD7S8EmxGTJYAKtTVhAW5Q5pge4j1").unwrap();
     pub static ref TOKEN_PROGRAM: Pubkey =
         Pubkey::from_str("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA").unwrap();
+    pub static ref TOKEN_2022_PROGRAM: Pubkey =
+        Pubkey::from_str("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb").unwrap();
     pub static ref SOL: Pubkey = Pubkey::from_str(WSOL_ADDRESS).unwrap();
     pub static ref MIN_LIQUIDITY: u64 = 25_000_000_000; // 25 billion lamports ~ 4200$
     pub static ref MAX_LIQUIDITY: u64 = 150_000_000_000; // 150 billion lamports ~ 25000$
     pub static ref LAMPORTS_PER_SOL: f64 = 1_000_000_000.;
 }
 
+/// SOL a wallet needs on top of the trade amount to pay for fees and ATA rent
+pub const WALLET_FEE_BUFFER_LAMPORTS: u64 = 10_000_000;
+
+/// Base fee paid per transaction signature
+pub const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+/// Priority fee of the swap transactions, in micro-lamports per compute unit
+pub const COMPUTE_UNIT_PRICE_MICRO_LAMPORTS: u64 = 130_000;
+
+/// Compute units requested by the swap transactions
+pub const COMPUTE_UNIT_LIMIT: u32 = 70_000;
+
 pub const AMM_ID_INDEX_IN_INIT_INSTRUCTION: usize = 4;
 pub const MARKET_ID_INDEX_IN_INIT_INSTRUCTION: usize = 16;
 pub const BASE_MINT_INDEX_IN_INIT_INSTRUCTION: usize = 8;
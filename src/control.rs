@@ -0,0 +1,114 @@
+use std::{net::SocketAddr, str::FromStr};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use coerce::actor::LocalActorRef;
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::actors::listener::actor::{
+    Blacklist, ForceSell, GetStatus, Listener, SetPaused, Shutdown, ShutdownPolicy, UpdateSettings,
+};
+
+/// Serves the JSON control API of a running listener:
+///
+/// - `GET /status` settings and running swappers
+/// - `POST /pause` and `POST /resume` new entries
+/// - `POST /settings` `{"max_swappers": 2, "trade_amount": 0.01}`, both optional
+/// - `POST /swappers/:amm_id/sell` force-sells a position
+/// - `POST /mints/:mint/blacklist` skips the mint and sells its positions
+/// - `POST /shutdown` `{"policy": "liquidate"}`, optional, defaults to `drain`
+pub async fn serve(addr: SocketAddr, listener: LocalActorRef<Listener>) -> Result<(), eyre::Error> {
+    let app = Router::new()
+        .route("/status", get(status))
+        .route("/pause", post(pause))
+        .route("/resume", post(resume))
+        .route("/settings", post(settings))
+        .route("/swappers/:amm_id/sell", post(sell))
+        .route("/mints/:mint/blacklist", post(blacklist))
+        .route("/shutdown", post(shutdown))
+        .with_state(listener);
+    let tcp_listener = tokio::net::TcpListener::bind(addr).await?;
+    tracing::info!("serving control API on http://{}", addr);
+    axum::serve(tcp_listener, app).await?;
+    Ok(())
+}
+
+type ApiResult<T> = Result<T, (StatusCode, String)>;
+
+fn unavailable(e: impl std::fmt::Display) -> (StatusCode, String) {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        format!("listener unavailable: {}", e),
+    )
+}
+
+async fn status(State(listener): State<LocalActorRef<Listener>>) -> ApiResult<Response> {
+    let status = listener.send(GetStatus).await.map_err(unavailable)?;
+    Ok(Json(status).into_response())
+}
+
+async fn pause(State(listener): State<LocalActorRef<Listener>>) -> ApiResult<StatusCode> {
+    listener.send(SetPaused(true)).await.map_err(unavailable)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn resume(State(listener): State<LocalActorRef<Listener>>) -> ApiResult<StatusCode> {
+    listener.send(SetPaused(false)).await.map_err(unavailable)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn settings(
+    State(listener): State<LocalActorRef<Listener>>,
+    Json(settings): Json<UpdateSettings>,
+) -> ApiResult<StatusCode> {
+    listener
+        .send(settings)
+        .await
+        .map_err(unavailable)?
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn sell(
+    State(listener): State<LocalActorRef<Listener>>,
+    Path(amm_id): Path<String>,
+) -> ApiResult<StatusCode> {
+    let amm_id = Pubkey::from_str(&amm_id)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("invalid amm id: {}", e)))?;
+    listener
+        .send(ForceSell(amm_id))
+        .await
+        .map_err(unavailable)?
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+async fn blacklist(
+    State(listener): State<LocalActorRef<Listener>>,
+    Path(mint): Path<String>,
+) -> ApiResult<StatusCode> {
+    let mint = Pubkey::from_str(&mint)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("invalid mint: {}", e)))?;
+    listener.send(Blacklist(mint)).await.map_err(unavailable)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Deserialize)]
+struct ShutdownRequest {
+    policy: ShutdownPolicy,
+}
+
+async fn shutdown(
+    State(listener): State<LocalActorRef<Listener>>,
+    request: Option<Json<ShutdownRequest>>,
+) -> ApiResult<StatusCode> {
+    let policy = request.map_or(ShutdownPolicy::Drain, |Json(request)| request.policy);
+    listener.send(Shutdown(policy)).await.map_err(unavailable)?;
+    Ok(StatusCode::ACCEPTED)
+}
@@ -0,0 +1,737 @@
+use std::{
+    path::Path,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use eyre::{eyre, Result};
+use rusqlite::{params, params_from_iter, Connection, Row, ToSql};
+use serde::Serialize;
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcTransactionConfig};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use solana_transaction_status::{
+    option_serializer::OptionSerializer, EncodedConfirmedTransactionWithStatusMeta,
+    EncodedTransaction, UiTransactionEncoding, UiTransactionTokenBalance,
+};
+
+use crate::{
+    constants::{LAMPORTS_PER_SIGNATURE, RAYDIUM_AUTHORITY_V4, SOL},
+    types::ProgramConfig,
+    utils::{get_transaction_from_signature, now_ms},
+};
+
+/// Attempts at fetching a confirmed swap, the RPC node may lag behind the
+/// confirmation it reported
+const GET_TRANSACTION_RETRIES: usize = 5;
+const GET_TRANSACTION_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum TradeSide {
+    /// SOL in, tokens out
+    Buy,
+    /// Tokens in, SOL out
+    Sell,
+}
+
+impl TradeSide {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TradeSide::Buy => "buy",
+            TradeSide::Sell => "sell",
+        }
+    }
+}
+
+impl FromStr for TradeSide {
+    type Err = eyre::Error;
+
+    fn from_str(side: &str) -> Result<Self, Self::Err> {
+        match side {
+            "buy" => Ok(TradeSide::Buy),
+            "sell" => Ok(TradeSide::Sell),
+            _ => Err(eyre!("unknown trade side {}", side)),
+        }
+    }
+}
+
+/// Sign of the realized PnL of a sell
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TradeOutcome {
+    Win,
+    Loss,
+}
+
+/// Swap as settled on chain, read from the confirmed transaction meta
+#[derive(Debug, Clone, Copy)]
+pub struct ConfirmedSwap {
+    pub slot: u64,
+    /// Lamports paid to the pool on buys, received from it on sells
+    pub lamports: u64,
+    /// Tokens received by the wallet on buys, sent from it on sells, in base
+    /// units net of Token-2022 transfer fees
+    pub tokens: u64,
+    pub base_fee_lamports: u64,
+    pub priority_fee_lamports: u64,
+}
+
+impl ConfirmedSwap {
+    /// Reads the SOL side from the pool WSOL vault balance change and the
+    /// token side from the `wallet` balance change of `mint`
+    pub fn from_transaction(
+        transaction: &EncodedConfirmedTransactionWithStatusMeta,
+        wallet: &Pubkey,
+        mint: &Pubkey,
+    ) -> Result<Self> {
+        let meta = transaction
+            .transaction
+            .meta
+            .as_ref()
+            .ok_or_else(|| eyre!("transaction has no meta"))?;
+        if meta.err.is_some() {
+            return Err(eyre!("transaction failed: {:?}", meta.err));
+        }
+        let signatures = match &transaction.transaction.transaction {
+            EncodedTransaction::Json(transaction) => transaction.signatures.len() as u64,
+            _ => return Err(eyre!("unexpected transaction encoding")),
+        };
+
+        let pre_token_balances = token_balances(&meta.pre_token_balances);
+        let post_token_balances = token_balances(&meta.post_token_balances);
+        let balance_change = |owner: &Pubkey, mint: &Pubkey| {
+            token_balance(post_token_balances, owner, mint) as i128
+                - token_balance(pre_token_balances, owner, mint) as i128
+        };
+
+        let base_fee_lamports = LAMPORTS_PER_SIGNATURE * signatures;
+        Ok(Self {
+            slot: transaction.slot,
+            lamports: balance_change(&RAYDIUM_AUTHORITY_V4, &SOL).unsigned_abs() as u64,
+            tokens: balance_change(wallet, mint).unsigned_abs() as u64,
+            base_fee_lamports,
+            priority_fee_lamports: meta.fee.saturating_sub(base_fee_lamports),
+        })
+    }
+
+    /// Fetches the confirmed swap transaction and reads it
+    pub async fn fetch(
+        client: &RpcClient,
+        signature: Signature,
+        wallet: &Pubkey,
+        mint: &Pubkey,
+    ) -> Result<Self> {
+        let mut attempt = 1;
+        let transaction = loop {
+            let transaction = get_transaction_from_signature(
+                client,
+                signature,
+                RpcTransactionConfig {
+                    encoding: Some(UiTransactionEncoding::Json),
+                    max_supported_transaction_version: Some(0),
+                    commitment: Some(CommitmentConfig::confirmed()),
+                },
+            )
+            .await;
+            match transaction {
+                Ok(transaction) => break transaction,
+                Err(e) if attempt >= GET_TRANSACTION_RETRIES => return Err(e),
+                Err(_) => {
+                    attempt += 1;
+                    tokio::time::sleep(GET_TRANSACTION_RETRY_DELAY).await;
+                }
+            }
+        };
+        Self::from_transaction(&transaction, wallet, mint)
+    }
+
+    /// Swap filled by the paper engine, which has no slot and only charges
+    /// the base fee
+    pub fn paper(lamports: u64, tokens: u64) -> Self {
+        Self {
+            slot: 0,
+            lamports,
+            tokens,
+            base_fee_lamports: LAMPORTS_PER_SIGNATURE,
+            priority_fee_lamports: 0,
+        }
+    }
+
+    pub fn fee_lamports(&self) -> u64 {
+        self.base_fee_lamports + self.priority_fee_lamports
+    }
+}
+
+fn token_balances(
+    balances: &OptionSerializer<Vec<UiTransactionTokenBalance>>,
+) -> &[UiTransactionTokenBalance] {
+    match balances {
+        OptionSerializer::Some(balances) => balances,
+        _ => &[],
+    }
+}
+
+/// Sum of the `mint` balances held by `owner`, accounts missing from the
+/// list did not exist
+fn token_balance(balances: &[UiTransactionTokenBalance], owner: &Pubkey, mint: &Pubkey) -> u64 {
+    let owner = owner.to_string();
+    let mint = mint.to_string();
+    balances
+        .iter()
+        .filter(|balance| {
+            balance.mint == mint
+                && matches!(&balance.owner, OptionSerializer::Some(o) if *o == owner)
+        })
+        .filter_map(|balance| balance.ui_token_amount.amount.parse::<u64>().ok())
+        .sum()
+}
+
+/// Journaled swap
+#[derive(Debug, Clone, Serialize)]
+pub struct JournalEntry {
+    pub id: Option<i64>,
+    pub at_ms: u64,
+    pub side: TradeSide,
+    pub wallet: String,
+    pub amm_id: String,
+    /// Token traded against SOL
+    pub mint: String,
+    pub signature: String,
+    pub slot: u64,
+    /// Lamports on buys, tokens in base units on sells
+    pub amount_in: u64,
+    /// Tokens in base units on buys, lamports on sells
+    pub amount_out: u64,
+    pub base_fee_lamports: u64,
+    pub priority_fee_lamports: u64,
+    /// Tips are not sent yet, always 0
+    pub tip_lamports: u64,
+    /// Sale proceeds net of fees minus the average cost of the tokens sold,
+    /// 0 on buys and unknown when the tokens were not bought by the bot
+    pub realized_pnl_lamports: Option<i64>,
+}
+
+/// Filters of [`Journal::query`], dates are UTC and inclusive
+#[derive(Debug, Default)]
+pub struct JournalQuery {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub mint: Option<Pubkey>,
+    pub side: Option<TradeSide>,
+    pub outcome: Option<TradeOutcome>,
+}
+
+/// Append-only journal of the swaps sent by the bot, see `JOURNAL_DB_PATH`
+pub struct Journal {
+    connection: Mutex<Connection>,
+}
+
+impl Journal {
+    /// Journal at `JOURNAL_DB_PATH` if configured
+    ///
+    /// # Panic
+    ///
+    /// Panics if the database cannot be opened
+    pub fn from_config(config: &ProgramConfig) -> Option<Arc<Self>> {
+        config.journal_db_path.as_deref().map(|path| {
+            Arc::new(Journal::open(Path::new(path)).expect("failed to open journal database"))
+        })
+    }
+
+    pub fn open(path: &Path) -> Result<Self> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS journal (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                at_ms INTEGER NOT NULL,
+                side TEXT NOT NULL,
+                wallet TEXT NOT NULL,
+                amm_id TEXT NOT NULL,
+                mint TEXT NOT NULL,
+                signature TEXT NOT NULL UNIQUE,
+                slot INTEGER NOT NULL,
+                amount_in INTEGER NOT NULL,
+                amount_out INTEGER NOT NULL,
+                base_fee_lamports INTEGER NOT NULL,
+                priority_fee_lamports INTEGER NOT NULL,
+                tip_lamports INTEGER NOT NULL,
+                realized_pnl_lamports INTEGER
+            );
+            CREATE INDEX IF NOT EXISTS journal_mint ON journal (mint);
+            CREATE INDEX IF NOT EXISTS journal_at_ms ON journal (at_ms);
+            CREATE TRIGGER IF NOT EXISTS journal_no_update BEFORE UPDATE ON journal
+            BEGIN SELECT RAISE(ABORT, 'journal is append-only'); END;
+            CREATE TRIGGER IF NOT EXISTS journal_no_delete BEFORE DELETE ON journal
+            BEGIN SELECT RAISE(ABORT, 'journal is append-only'); END;",
+        )?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// Journals a confirmed swap, computing the realized PnL of sells
+    /// against the weighted average cost of the tokens the journaled swaps
+    /// of the same wallet and mint left
+    pub fn append(
+        &self,
+        side: TradeSide,
+        wallet: &Pubkey,
+        amm_id: &Pubkey,
+        mint: &Pubkey,
+        signature: &Signature,
+        swap: &ConfirmedSwap,
+    ) -> Result<JournalEntry> {
+        let connection = self.connection.lock().unwrap();
+        let (amount_in, amount_out, realized_pnl_lamports) = match side {
+            TradeSide::Buy => (swap.lamports, swap.tokens, Some(0)),
+            TradeSide::Sell => {
+                let (tokens_held, cost_lamports) = held_tokens(&connection, wallet, mint)?;
+                let realized_pnl_lamports = (tokens_held > 0).then(|| {
+                    let cost_basis = cost_basis(tokens_held, cost_lamports, swap.tokens as u128);
+                    (swap.lamports as i128 - swap.fee_lamports() as i128 - cost_basis as i128)
+                        as i64
+                });
+                (swap.tokens, swap.lamports, realized_pnl_lamports)
+            }
+        };
+
+        let entry = JournalEntry {
+            id: None,
+            at_ms: now_ms(),
+            side,
+            wallet: wallet.to_string(),
+            amm_id: amm_id.to_string(),
+            mint: mint.to_string(),
+            signature: signature.to_string(),
+            slot: swap.slot,
+            amount_in,
+            amount_out,
+            base_fee_lamports: swap.base_fee_lamports,
+            priority_fee_lamports: swap.priority_fee_lamports,
+            tip_lamports: 0,
+            realized_pnl_lamports,
+        };
+        connection.execute(
+            "INSERT INTO journal (
+                at_ms, side, wallet, amm_id, mint, signature, slot, amount_in, amount_out,
+                base_fee_lamports, priority_fee_lamports, tip_lamports, realized_pnl_lamports
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            params![
+                entry.at_ms as i64,
+                entry.side.as_str(),
+                entry.wallet,
+                entry.amm_id,
+                entry.mint,
+                entry.signature,
+                entry.slot as i64,
+                entry.amount_in as i64,
+                entry.amount_out as i64,
+                entry.base_fee_lamports as i64,
+                entry.priority_fee_lamports as i64,
+                entry.tip_lamports as i64,
+                entry.realized_pnl_lamports,
+            ],
+        )?;
+        Ok(entry)
+    }
+
+    /// Entries matching every given filter, oldest first
+    pub fn query(&self, query: &JournalQuery) -> Result<Vec<JournalEntry>> {
+        let mut conditions = vec![];
+        let mut values: Vec<Box<dyn ToSql>> = vec![];
+        if let Some(from) = query.from {
+            values.push(Box::new(start_of_day_ms(from)));
+            conditions.push(format!("at_ms >= ?{}", values.len()));
+        }
+        if let Some(to) = query.to.and_then(|to| to.succ_opt()) {
+            values.push(Box::new(start_of_day_ms(to)));
+            conditions.push(format!("at_ms < ?{}", values.len()));
+        }
+        if let Some(mint) = &query.mint {
+            values.push(Box::new(mint.to_string()));
+            conditions.push(format!("mint = ?{}", values.len()));
+        }
+        if let Some(side) = &query.side {
+            values.push(Box::new(side.as_str()));
+            conditions.push(format!("side = ?{}", values.len()));
+        }
+        match query.outcome {
+            Some(TradeOutcome::Win) => {
+                conditions.push("side = 'sell' AND realized_pnl_lamports > 0".to_string())
+            }
+            Some(TradeOutcome::Loss) => {
+                conditions.push("side = 'sell' AND realized_pnl_lamports <= 0".to_string())
+            }
+            None => {}
+        }
+
+        let mut sql = "SELECT * FROM journal".to_string();
+        if !conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&conditions.join(" AND "));
+        }
+        sql.push_str(" ORDER BY id");
+
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare(&sql)?;
+        let rows = statement.query_map(params_from_iter(values.iter()), |row| {
+            Ok(entry_from_row(row))
+        })?;
+        rows.map(|entry| entry?).collect()
+    }
+}
+
+/// Tokens of `mint` the journaled swaps of the wallet left and their cost
+/// in lamports, fees included. Each sell takes its share of the average cost.
+fn held_tokens(connection: &Connection, wallet: &Pubkey, mint: &Pubkey) -> Result<(u128, u128)> {
+    let mut statement = connection.prepare(
+        "SELECT side, amount_in, amount_out,
+            base_fee_lamports + priority_fee_lamports + tip_lamports
+        FROM journal WHERE wallet = ?1 AND mint = ?2 ORDER BY id",
+    )?;
+    let mut rows = statement.query(params![wallet.to_string(), mint.to_string()])?;
+
+    let (mut tokens, mut cost_lamports) = (0_u128, 0_u128);
+    while let Some(row) = rows.next()? {
+        let amount_in = row.get::<_, i64>(1)? as u128;
+        let amount_out = row.get::<_, i64>(2)? as u128;
+        match row.get::<_, String>(0)?.parse()? {
+            TradeSide::Buy => {
+                tokens += amount_out;
+                cost_lamports += amount_in + row.get::<_, i64>(3)? as u128;
+            }
+            TradeSide::Sell => {
+                cost_lamports -= cost_basis(tokens, cost_lamports, amount_in);
+                tokens = tokens.saturating_sub(amount_in);
+            }
+        }
+    }
+    Ok((tokens, cost_lamports))
+}
+
+/// Average cost of `tokens_sold` out of `tokens_held`, tokens sold beyond
+/// the ones held were not bought by the bot and cost nothing
+fn cost_basis(tokens_held: u128, cost_lamports: u128, tokens_sold: u128) -> u128 {
+    if tokens_held == 0 {
+        return 0;
+    }
+    cost_lamports * tokens_sold.min(tokens_held) / tokens_held
+}
+
+fn start_of_day_ms(date: NaiveDate) -> i64 {
+    date.and_hms_opt(0, 0, 0)
+        .unwrap_or_default()
+        .and_utc()
+        .timestamp_millis()
+}
+
+fn entry_from_row(row: &Row) -> Result<JournalEntry> {
+    let unsigned = |column: &str| -> Result<u64> { Ok(row.get::<_, i64>(column)? as u64) };
+
+    Ok(JournalEntry {
+        id: Some(row.get("id")?),
+        at_ms: unsigned("at_ms")?,
+        side: row.get::<_, String>("side")?.parse()?,
+        wallet: row.get("wallet")?,
+        amm_id: row.get("amm_id")?,
+        mint: row.get("mint")?,
+        signature: row.get("signature")?,
+        slot: unsigned("slot")?,
+        amount_in: unsigned("amount_in")?,
+        amount_out: unsigned("amount_out")?,
+        base_fee_lamports: unsigned("base_fee_lamports")?,
+        priority_fee_lamports: unsigned("priority_fee_lamports")?,
+        tip_lamports: unsigned("tip_lamports")?,
+        realized_pnl_lamports: row.get("realized_pnl_lamports")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::DateTime;
+
+    fn journal() -> Journal {
+        Journal::open(Path::new(":memory:")).unwrap()
+    }
+
+    fn ui_token_balance(owner: &Pubkey, mint: &Pubkey, amount: u64) -> serde_json::Value {
+        serde_json::json!({
+            "accountIndex": 1,
+            "mint": mint.to_string(),
+            "uiTokenAmount": {
+                "uiAmount": null,
+                "decimals": 0,
+                "amount": amount.to_string(),
+                "uiAmountString": amount.to_string(),
+            },
+            "owner": owner.to_string(),
+            "programId": spl_token::id().to_string(),
+        })
+    }
+
+    /// Confirmed transaction of a single signature with the given meta error
+    /// and token balances
+    fn transaction(
+        err: Option<serde_json::Value>,
+        fee: u64,
+        pre_token_balances: Vec<serde_json::Value>,
+        post_token_balances: Vec<serde_json::Value>,
+    ) -> EncodedConfirmedTransactionWithStatusMeta {
+        let status = match &err {
+            Some(err) => serde_json::json!({ "Err": err }),
+            None => serde_json::json!({ "Ok": null }),
+        };
+        serde_json::from_value(serde_json::json!({
+            "slot": 42,
+            "blockTime": null,
+            "transaction": {
+                "signatures": [Signature::new_unique().to_string()],
+                "message": {
+                    "header": {
+                        "numRequiredSignatures": 1,
+                        "numReadonlySignedAccounts": 0,
+                        "numReadonlyUnsignedAccounts": 0,
+                    },
+                    "accountKeys": [],
+                    "recentBlockhash": Pubkey::default().to_string(),
+                    "instructions": [],
+                },
+            },
+            "meta": {
+                "err": err,
+                "status": status,
+                "fee": fee,
+                "preBalances": [],
+                "postBalances": [],
+                "innerInstructions": [],
+                "logMessages": [],
+                "preTokenBalances": pre_token_balances,
+                "postTokenBalances": post_token_balances,
+                "rewards": [],
+            },
+            "version": "legacy",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn reads_swap_from_balance_changes() {
+        let wallet = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let transaction = transaction(
+            None,
+            15_000,
+            vec![ui_token_balance(
+                &RAYDIUM_AUTHORITY_V4,
+                &SOL,
+                10_000_000_000,
+            )],
+            vec![
+                ui_token_balance(&RAYDIUM_AUTHORITY_V4, &SOL, 11_000_000_000),
+                ui_token_balance(&wallet, &mint, 5_000_000),
+            ],
+        );
+
+        let swap = ConfirmedSwap::from_transaction(&transaction, &wallet, &mint).unwrap();
+
+        assert_eq!(swap.slot, 42);
+        assert_eq!(swap.lamports, 1_000_000_000);
+        assert_eq!(swap.tokens, 5_000_000);
+        assert_eq!(swap.base_fee_lamports, 5_000);
+        assert_eq!(swap.priority_fee_lamports, 10_000);
+    }
+
+    #[test]
+    fn failed_transaction_is_not_a_swap() {
+        let transaction = transaction(
+            Some(serde_json::json!({ "InstructionError": [2, { "Custom": 30 }] })),
+            5_000,
+            vec![],
+            vec![],
+        );
+
+        let swap = ConfirmedSwap::from_transaction(&transaction, &Pubkey::new_unique(), &SOL);
+
+        assert!(swap.is_err());
+    }
+
+    /// Appends a paper swap, which pays the 5000 lamports base fee
+    fn append(
+        journal: &Journal,
+        side: TradeSide,
+        wallet: &Pubkey,
+        mint: &Pubkey,
+        lamports: u64,
+        tokens: u64,
+    ) -> JournalEntry {
+        journal
+            .append(
+                side,
+                wallet,
+                &Pubkey::new_unique(),
+                mint,
+                &Signature::new_unique(),
+                &ConfirmedSwap::paper(lamports, tokens),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn realized_pnl_uses_the_average_cost_of_the_tokens_held() {
+        let journal = journal();
+        let wallet = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+
+        // 1000 tokens for 1 SOL with fees
+        let buy = append(&journal, TradeSide::Buy, &wallet, &mint, 999_995_000, 1_000);
+        assert_eq!(buy.realized_pnl_lamports, Some(0));
+
+        // Half of them for 0.6 SOL net of fees, costing 0.5 SOL
+        let sell = append(&journal, TradeSide::Sell, &wallet, &mint, 600_005_000, 500);
+        assert_eq!(sell.realized_pnl_lamports, Some(100_000_000));
+
+        // 500 more for 2 SOL, the 1000 tokens held now cost 2.5 SOL
+        append(&journal, TradeSide::Buy, &wallet, &mint, 1_999_995_000, 500);
+        let sell = append(
+            &journal,
+            TradeSide::Sell,
+            &wallet,
+            &mint,
+            2_000_005_000,
+            1_000,
+        );
+        assert_eq!(sell.realized_pnl_lamports, Some(-500_000_000));
+
+        // Nothing left from the bot buys
+        let sell = append(&journal, TradeSide::Sell, &wallet, &mint, 1_000_000, 10);
+        assert_eq!(sell.realized_pnl_lamports, None);
+    }
+
+    #[test]
+    fn cost_is_tracked_per_wallet_and_mint() {
+        let journal = journal();
+        let wallet = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        append(&journal, TradeSide::Buy, &wallet, &mint, 999_995_000, 1_000);
+
+        let other_wallet = Pubkey::new_unique();
+        let sell = append(
+            &journal,
+            TradeSide::Sell,
+            &other_wallet,
+            &mint,
+            1_000_000,
+            10,
+        );
+        assert_eq!(sell.realized_pnl_lamports, None);
+        let other_mint = Pubkey::new_unique();
+        let sell = append(
+            &journal,
+            TradeSide::Sell,
+            &wallet,
+            &other_mint,
+            1_000_000,
+            10,
+        );
+        assert_eq!(sell.realized_pnl_lamports, None);
+    }
+
+    #[test]
+    fn queries_by_filters() {
+        let journal = journal();
+        let wallet = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let other_mint = Pubkey::new_unique();
+        append(&journal, TradeSide::Buy, &wallet, &mint, 999_995_000, 1_000);
+        let win = append(&journal, TradeSide::Sell, &wallet, &mint, 600_005_000, 500);
+        let loss = append(&journal, TradeSide::Sell, &wallet, &mint, 400_005_000, 500);
+        append(
+            &journal,
+            TradeSide::Buy,
+            &wallet,
+            &other_mint,
+            999_995_000,
+            1_000,
+        );
+
+        let signatures = |query: JournalQuery| -> Vec<String> {
+            journal
+                .query(&query)
+                .unwrap()
+                .into_iter()
+                .map(|entry| entry.signature)
+                .collect()
+        };
+
+        assert_eq!(signatures(JournalQuery::default()).len(), 4);
+        assert_eq!(
+            signatures(JournalQuery {
+                mint: Some(mint),
+                ..JournalQuery::default()
+            })
+            .len(),
+            3
+        );
+        assert_eq!(
+            signatures(JournalQuery {
+                side: Some(TradeSide::Sell),
+                ..JournalQuery::default()
+            }),
+            vec![win.signature.clone(), loss.signature.clone()]
+        );
+        assert_eq!(
+            signatures(JournalQuery {
+                outcome: Some(TradeOutcome::Win),
+                ..JournalQuery::default()
+            }),
+            vec![win.signature]
+        );
+        assert_eq!(
+            signatures(JournalQuery {
+                outcome: Some(TradeOutcome::Loss),
+                ..JournalQuery::default()
+            }),
+            vec![loss.signature]
+        );
+
+        let today = DateTime::from_timestamp_millis(now_ms() as i64)
+            .unwrap()
+            .date_naive();
+        assert_eq!(
+            signatures(JournalQuery {
+                from: Some(today),
+                to: Some(today),
+                ..JournalQuery::default()
+            })
+            .len(),
+            4
+        );
+        assert!(signatures(JournalQuery {
+            to: today.pred_opt(),
+            ..JournalQuery::default()
+        })
+        .is_empty());
+    }
+
+    #[test]
+    fn entries_are_append_only() {
+        let journal = journal();
+        append(
+            &journal,
+            TradeSide::Buy,
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            999_995_000,
+            1_000,
+        );
+
+        let connection = journal.connection.lock().unwrap();
+        assert!(connection.execute("DELETE FROM journal", []).is_err());
+        assert!(connection
+            .execute("UPDATE journal SET amount_in = 0", [])
+            .is_err());
+    }
+}
@@ -0,0 +1,109 @@
+use std::time::Instant;
+
+use solana_sdk::pubkey::Pubkey;
+
+use crate::metrics::{LAUNCH_STAGE_SECONDS, LOG_TO_CONFIRM_SECONDS};
+
+/// Stages of a launch after its creation log is received, in order
+#[derive(Debug, Clone, Copy)]
+pub enum Stage {
+    TransactionFetched,
+    AccountsFetched,
+    SwapperStarted,
+    LiquidityChecked,
+    TransactionSigned,
+    TransactionSent,
+    TransactionConfirmed,
+}
+
+impl Stage {
+    const ALL: [Stage; 7] = [
+        Stage::TransactionFetched,
+        Stage::AccountsFetched,
+        Stage::SwapperStarted,
+        Stage::LiquidityChecked,
+        Stage::TransactionSigned,
+        Stage::TransactionSent,
+        Stage::TransactionConfirmed,
+    ];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Stage::TransactionFetched => "transaction_fetched",
+            Stage::AccountsFetched => "accounts_fetched",
+            Stage::SwapperStarted => "swapper_started",
+            Stage::LiquidityChecked => "liquidity_checked",
+            Stage::TransactionSigned => "transaction_signed",
+            Stage::TransactionSent => "transaction_sent",
+            Stage::TransactionConfirmed => "transaction_confirmed",
+        }
+    }
+}
+
+/// When each stage of a launch was reached, from the creation log to the
+/// buy confirmation
+#[derive(Debug, Clone)]
+pub struct LaunchLatency {
+    log_received: Instant,
+    stages: [Option<Instant>; Stage::ALL.len()],
+}
+
+impl LaunchLatency {
+    pub fn new(log_received: Instant) -> Self {
+        Self {
+            log_received,
+            stages: [None; Stage::ALL.len()],
+        }
+    }
+
+    pub fn log_received(&self) -> Instant {
+        self.log_received
+    }
+
+    pub fn stamp(&mut self, stage: Stage) {
+        self.stamp_at(stage, Instant::now());
+    }
+
+    pub fn stamp_at(&mut self, stage: Stage, at: Instant) {
+        self.stages[stage as usize] = Some(at);
+    }
+
+    /// Emits the time spent reaching each stage from the previous one as a
+    /// single event, and feeds it to the stage histograms. Stages that were
+    /// not reached are left out.
+    pub fn report(&self, amm_id: &Pubkey, slot: Option<u64>) {
+        let mut durations_ms = [None; Stage::ALL.len()];
+        let mut previous = self.log_received;
+        for stage in Stage::ALL {
+            let Some(at) = self.stages[stage as usize] else {
+                continue;
+            };
+            let duration = at.saturating_duration_since(previous);
+            LAUNCH_STAGE_SECONDS
+                .with_label_values(&[stage.as_str()])
+                .observe(duration.as_secs_f64());
+            durations_ms[stage as usize] = Some(duration.as_millis() as u64);
+            previous = at;
+        }
+
+        let confirmed = self.stages[Stage::TransactionConfirmed as usize];
+        if let Some(confirmed) = confirmed {
+            LOG_TO_CONFIRM_SECONDS.observe((confirmed - self.log_received).as_secs_f64());
+        }
+
+        let stage_ms = |stage: Stage| durations_ms[stage as usize];
+        tracing::info!(
+            %amm_id,
+            transaction_fetched_ms = stage_ms(Stage::TransactionFetched),
+            accounts_fetched_ms = stage_ms(Stage::AccountsFetched),
+            swapper_started_ms = stage_ms(Stage::SwapperStarted),
+            liquidity_checked_ms = stage_ms(Stage::LiquidityChecked),
+            transaction_signed_ms = stage_ms(Stage::TransactionSigned),
+            transaction_sent_ms = stage_ms(Stage::TransactionSent),
+            transaction_confirmed_ms = stage_ms(Stage::TransactionConfirmed),
+            total_ms = previous.saturating_duration_since(self.log_received).as_millis() as u64,
+            slot,
+            "launch latency"
+        );
+    }
+}
@@ -1,11 +1,21 @@
 mod actors;
 mod constants;
+mod control;
+mod journal;
+mod latency;
+mod metrics;
+mod positions;
+mod recording;
+mod sender;
+mod signer;
 mod subcommands;
+mod tui;
 mod types;
 mod utils;
 mod websocket;
 
 use {
+    signer::{load_wallets, SignerError, SignerSource, SignerUse, TransactionSigner},
     subcommands::{Args, Subcommands},
     types::ProgramConfig,
 };
@@ -13,6 +23,7 @@ use {
 use clap::Parser;
 
 use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::signature::Keypair;
 
 use utils::init_logging;
 
@@ -20,16 +31,80 @@ use std::sync::Arc;
 
 #[tokio::main]
 async fn main() {
-    init_logging();
+    let args = Args::parse();
 
-    let config = ProgramConfig::from_dotenv();
+    // The dashboard takes over the terminal
+    let console_logs = !matches!(&args.command, Subcommands::Listen(listen) if listen.tui);
+    init_logging(
+        args.log_format,
+        console_logs,
+        args.log_dir.as_deref(),
+        args.log_rotation,
+    );
 
-    let args = Args::parse();
+    let config = ProgramConfig::from_dotenv();
 
     let client = Arc::new(RpcClient::new(config.http_rpc_url.clone()));
 
     match args.command {
-        Subcommands::Listen(listen) => listen.run(client, config).await,
-        Subcommands::InstantSwap(instant_swap) => instant_swap.run(client, config).await,
+        Subcommands::Backtest(backtest) => backtest.run().await,
+        Subcommands::Journal(journal) => journal.run(config),
+        Subcommands::Quote(quote) => quote.run(client).await,
+        Subcommands::Replay(replay) => replay.run().await,
+        Subcommands::Keystore(keystore) => keystore.run(load_keypair(&config)),
+        Subcommands::SigningService(signing_service) => {
+            signing_service.run(load_keypair(&config)).await
+        }
+        Subcommands::Listen(listen) => {
+            let signer = load_signer(&config, SignerUse::Trading);
+            let wallets = exit_on_signer_error(load_wallets(&config, signer, SignerUse::Trading));
+            listen.run(client, config, wallets).await
+        }
+        Subcommands::InstantSwap(instant_swap) => {
+            let signer = load_signer(&config, SignerUse::Trading);
+            instant_swap.run(client, config, signer).await
+        }
+        Subcommands::SellAll(sell_all) => {
+            let signer = load_signer(&config, SignerUse::Trading);
+            sell_all.run(client, config, signer).await
+        }
+        Subcommands::LookupTable(lookup_table) => {
+            let signer = load_signer(&config, SignerUse::Admin);
+            lookup_table.run(client, signer).await
+        }
+        Subcommands::Nonce(nonce) => {
+            let signer = load_signer(&config, SignerUse::Admin);
+            nonce.run(client, config, signer).await
+        }
+        Subcommands::ReclaimRent(reclaim_rent) => {
+            let signer = load_signer(&config, SignerUse::Admin);
+            let wallets = exit_on_signer_error(load_wallets(&config, signer, SignerUse::Admin));
+            reclaim_rent.run(client, wallets).await
+        }
+        Subcommands::Wallets(wallets) => {
+            let signer = load_signer(&config, SignerUse::Admin);
+            let trading_wallets =
+                exit_on_signer_error(load_wallets(&config, Arc::clone(&signer), SignerUse::Admin));
+            wallets.run(client, signer, trading_wallets).await
+        }
     }
 }
+
+/// Loads the configured signer, only for the subcommands that sign
+fn load_signer(config: &ProgramConfig, usage: SignerUse) -> Arc<dyn TransactionSigner> {
+    let signer_source = exit_on_signer_error(SignerSource::from_config(config));
+    exit_on_signer_error(signer_source.load(config, usage))
+}
+
+/// Loads the configured local keypair, for the subcommands that hold it
+fn load_keypair(config: &ProgramConfig) -> Keypair {
+    let signer_source = exit_on_signer_error(SignerSource::from_config(config));
+    exit_on_signer_error(signer_source.load_keypair())
+}
+
+fn exit_on_signer_error<T>(result: Result<T, SignerError>) -> T {
+    result.unwrap_or_else(|error| {
+        tracing::error!("failed to load signer: {}", error);
+        std::process::exit(1);
+    })
+}
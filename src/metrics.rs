@@ -0,0 +1,141 @@
+use std::net::SocketAddr;
+
+use axum::{http::header, response::IntoResponse, routing::get, Router};
+use lazy_static::lazy_static;
+use prometheus::{
+    core::Collector, exponential_buckets, register_gauge, register_histogram,
+    register_histogram_vec, register_int_counter, register_int_counter_vec, register_int_gauge,
+    Encoder, Gauge, Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge, TextEncoder,
+};
+
+lazy_static! {
+    pub static ref POOLS_DETECTED: IntCounter =
+        register_int_counter!("pools_detected_total", "Pool creations detected").unwrap();
+    pub static ref POOLS_FILTERED: IntCounterVec = register_int_counter_vec!(
+        "pools_filtered_total",
+        "Detected pools not traded, by reason",
+        &["reason"]
+    )
+    .unwrap();
+    pub static ref SWAPPERS_SPAWNED: IntCounter =
+        register_int_counter!("swappers_spawned_total", "Swappers spawned").unwrap();
+    pub static ref SWAPS_SENT: IntCounterVec = register_int_counter_vec!(
+        "swaps_sent_total",
+        "Swap transactions sent, by side",
+        &["side"]
+    )
+    .unwrap();
+    pub static ref SWAPS_LANDED: IntCounterVec = register_int_counter_vec!(
+        "swaps_landed_total",
+        "Swap transactions confirmed, by side",
+        &["side"]
+    )
+    .unwrap();
+    pub static ref SWAPS_FAILED: IntCounterVec = register_int_counter_vec!(
+        "swaps_failed_total",
+        "Swap transactions that failed or were not confirmed, by side",
+        &["side"]
+    )
+    .unwrap();
+    pub static ref WEBSOCKET_RECONNECTS: IntCounter = register_int_counter!(
+        "websocket_reconnects_total",
+        "Reconnections of the logs subscription"
+    )
+    .unwrap();
+    pub static ref RPC_ERRORS: IntCounterVec = register_int_counter_vec!(
+        "rpc_errors_total",
+        "Failed RPC requests, by method",
+        &["method"]
+    )
+    .unwrap();
+    pub static ref DETECTION_TO_SEND_SECONDS: Histogram = register_histogram!(
+        "detection_to_send_seconds",
+        "Time from the pool creation log to sending the buy",
+        exponential_buckets(0.05, 2., 10).unwrap()
+    )
+    .unwrap();
+    pub static ref SEND_TO_CONFIRM_SECONDS: Histogram = register_histogram!(
+        "send_to_confirm_seconds",
+        "Time from sending a swap to its confirmation",
+        exponential_buckets(0.1, 2., 10).unwrap()
+    )
+    .unwrap();
+    pub static ref LAUNCH_STAGE_SECONDS: HistogramVec = register_histogram_vec!(
+        "launch_stage_seconds",
+        "Time to reach each stage of a launch from the previous one",
+        &["stage"],
+        exponential_buckets(0.005, 2., 12).unwrap()
+    )
+    .unwrap();
+    pub static ref LOG_TO_CONFIRM_SECONDS: Histogram = register_histogram!(
+        "log_to_confirm_seconds",
+        "Time from the pool creation log to the buy confirmation",
+        exponential_buckets(0.1, 2., 10).unwrap()
+    )
+    .unwrap();
+    pub static ref OPEN_POSITIONS: IntGauge =
+        register_int_gauge!("open_positions", "Positions held by running swappers").unwrap();
+    pub static ref UNREALIZED_PNL_SOL: Gauge = register_gauge!(
+        "unrealized_pnl_sol",
+        "Unrealized PnL of the open positions at the last price checks, in SOL"
+    )
+    .unwrap();
+    pub static ref REALIZED_PNL_SOL: Gauge = register_gauge!(
+        "realized_pnl_sol",
+        "PnL of the positions sold since start, net of fees, in SOL"
+    )
+    .unwrap();
+}
+
+/// Counts a failed RPC request
+pub fn rpc_error(method: &str) {
+    RPC_ERRORS.with_label_values(&[method]).inc();
+}
+
+/// Failed RPC requests of all methods
+pub fn rpc_errors_total() -> u64 {
+    RPC_ERRORS
+        .collect()
+        .iter()
+        .flat_map(|family| family.get_metric())
+        .map(|metric| metric.get_counter().get_value() as u64)
+        .sum()
+}
+
+/// Serves the metrics in the Prometheus text format on `/metrics`
+pub async fn serve(addr: SocketAddr) -> Result<(), eyre::Error> {
+    // Metrics are registered on first use, export them all from the start
+    lazy_static::initialize(&POOLS_DETECTED);
+    lazy_static::initialize(&POOLS_FILTERED);
+    lazy_static::initialize(&SWAPPERS_SPAWNED);
+    lazy_static::initialize(&SWAPS_SENT);
+    lazy_static::initialize(&SWAPS_LANDED);
+    lazy_static::initialize(&SWAPS_FAILED);
+    lazy_static::initialize(&WEBSOCKET_RECONNECTS);
+    lazy_static::initialize(&RPC_ERRORS);
+    lazy_static::initialize(&DETECTION_TO_SEND_SECONDS);
+    lazy_static::initialize(&SEND_TO_CONFIRM_SECONDS);
+    lazy_static::initialize(&LAUNCH_STAGE_SECONDS);
+    lazy_static::initialize(&LOG_TO_CONFIRM_SECONDS);
+    lazy_static::initialize(&OPEN_POSITIONS);
+    lazy_static::initialize(&UNREALIZED_PNL_SOL);
+    lazy_static::initialize(&REALIZED_PNL_SOL);
+
+    let app = Router::new().route("/metrics", get(metrics));
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    tracing::info!("serving metrics on http://{}/metrics", addr);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+async fn metrics() -> impl IntoResponse {
+    let encoder = TextEncoder::new();
+    let mut buffer = vec![];
+    if let Err(e) = encoder.encode(&prometheus::gather(), &mut buffer) {
+        tracing::error!("failed to encode metrics: {:?}", e);
+    }
+    (
+        [(header::CONTENT_TYPE, encoder.format_type().to_string())],
+        buffer,
+    )
+}
@@ -0,0 +1,331 @@
+use std::{path::Path, str::FromStr, sync::Mutex};
+
+use rusqlite::{params, Connection, Row};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+use crate::{actors::swapper::actor::PoolInitTxInfos, utils::now_ms};
+
+/// Lifecycle of a position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionStatus {
+    /// Bought, the exit strategy is running
+    Open,
+    /// Sold by the exit strategy
+    Sold,
+    /// Given up on by the exit strategy, the tokens are still held
+    Expired,
+}
+
+impl PositionStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            PositionStatus::Open => "open",
+            PositionStatus::Sold => "sold",
+            PositionStatus::Expired => "expired",
+        }
+    }
+}
+
+impl FromStr for PositionStatus {
+    type Err = eyre::Error;
+
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status {
+            "open" => Ok(PositionStatus::Open),
+            "sold" => Ok(PositionStatus::Sold),
+            "expired" => Ok(PositionStatus::Expired),
+            _ => Err(eyre::eyre!("unknown position status {}", status)),
+        }
+    }
+}
+
+/// Position taken by a swapper in a pool, enough to resume managing its
+/// exit after a restart
+#[derive(Debug, Clone)]
+pub struct Position {
+    pub pool: PoolInitTxInfos,
+    /// Token bought against SOL
+    pub mint: Pubkey,
+    pub wallet: Pubkey,
+    /// SOL spent on the buy
+    pub entry_lamports: u64,
+    /// Tokens received, known from the first price check
+    pub entry_tokens: Option<u64>,
+    /// Lamports paid per base unit of the token
+    pub entry_price: Option<f64>,
+    pub buy_signature: Signature,
+    pub sell_signature: Option<Signature>,
+    /// Price checks done by the exit strategy
+    pub price_checks: u32,
+    pub status: PositionStatus,
+    pub opened_at_ms: u64,
+    pub closed_at_ms: Option<u64>,
+}
+
+/// Positions persisted in SQLite, see `POSITIONS_DB_PATH`
+pub struct PositionStore {
+    connection: Mutex<Connection>,
+}
+
+impl PositionStore {
+    pub fn open(path: &Path) -> Result<Self, eyre::Error> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS positions (
+                amm_id TEXT PRIMARY KEY,
+                market_id TEXT NOT NULL,
+                base_mint TEXT NOT NULL,
+                quote_mint TEXT NOT NULL,
+                mint TEXT NOT NULL,
+                wallet TEXT NOT NULL,
+                entry_lamports INTEGER NOT NULL,
+                entry_tokens INTEGER,
+                entry_price REAL,
+                buy_signature TEXT NOT NULL,
+                sell_signature TEXT,
+                price_checks INTEGER NOT NULL DEFAULT 0,
+                status TEXT NOT NULL,
+                opened_at_ms INTEGER NOT NULL,
+                closed_at_ms INTEGER
+            );
+            CREATE INDEX IF NOT EXISTS positions_status ON positions (status);",
+        )?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// Records a new position, replacing a previous one in the same pool
+    pub fn insert(&self, position: &Position) -> Result<(), eyre::Error> {
+        self.connection.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO positions (
+                amm_id, market_id, base_mint, quote_mint, mint, wallet, entry_lamports,
+                entry_tokens, entry_price, buy_signature, sell_signature, price_checks, status,
+                opened_at_ms, closed_at_ms
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            params![
+                position.pool.amm_id.to_string(),
+                position.pool.market_id.to_string(),
+                position.pool.base_mint.to_string(),
+                position.pool.quote_mint.to_string(),
+                position.mint.to_string(),
+                position.wallet.to_string(),
+                position.entry_lamports as i64,
+                position.entry_tokens.map(|tokens| tokens as i64),
+                position.entry_price,
+                position.buy_signature.to_string(),
+                position
+                    .sell_signature
+                    .map(|signature| signature.to_string()),
+                position.price_checks,
+                position.status.as_str(),
+                position.opened_at_ms as i64,
+                position
+                    .closed_at_ms
+                    .map(|closed_at_ms| closed_at_ms as i64),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Saves the exit strategy progress, and the entry once known
+    pub fn record_price_check(
+        &self,
+        amm_id: &Pubkey,
+        price_checks: u32,
+        entry_tokens: u64,
+        entry_price: f64,
+    ) -> Result<(), eyre::Error> {
+        self.connection.lock().unwrap().execute(
+            "UPDATE positions SET price_checks = ?2,
+                entry_tokens = COALESCE(entry_tokens, ?3),
+                entry_price = COALESCE(entry_price, ?4)
+            WHERE amm_id = ?1",
+            params![
+                amm_id.to_string(),
+                price_checks,
+                entry_tokens as i64,
+                entry_price
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn close(
+        &self,
+        amm_id: &Pubkey,
+        status: PositionStatus,
+        sell_signature: Option<Signature>,
+    ) -> Result<(), eyre::Error> {
+        self.connection.lock().unwrap().execute(
+            "UPDATE positions SET status = ?2, sell_signature = ?3, closed_at_ms = ?4
+            WHERE amm_id = ?1",
+            params![
+                amm_id.to_string(),
+                status.as_str(),
+                sell_signature.map(|signature| signature.to_string()),
+                now_ms() as i64,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Positions whose exit strategy was still running
+    pub fn open_positions(&self) -> Result<Vec<Position>, eyre::Error> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection
+            .prepare("SELECT * FROM positions WHERE status = ?1 ORDER BY opened_at_ms")?;
+        let rows = statement.query_map(params![PositionStatus::Open.as_str()], |row| {
+            Ok(position_from_row(row))
+        })?;
+        rows.map(|position| position?).collect()
+    }
+}
+
+fn position_from_row(row: &Row) -> Result<Position, eyre::Error> {
+    let pubkey = |column: &str| -> Result<Pubkey, eyre::Error> {
+        Ok(Pubkey::from_str(&row.get::<_, String>(column)?)?)
+    };
+    let sell_signature: Option<String> = row.get("sell_signature")?;
+
+    Ok(Position {
+        pool: PoolInitTxInfos {
+            amm_id: pubkey("amm_id")?,
+            market_id: pubkey("market_id")?,
+            base_mint: pubkey("base_mint")?,
+            quote_mint: pubkey("quote_mint")?,
+        },
+        mint: pubkey("mint")?,
+        wallet: pubkey("wallet")?,
+        entry_lamports: row.get::<_, i64>("entry_lamports")? as u64,
+        entry_tokens: row
+            .get::<_, Option<i64>>("entry_tokens")?
+            .map(|tokens| tokens as u64),
+        entry_price: row.get("entry_price")?,
+        buy_signature: Signature::from_str(&row.get::<_, String>("buy_signature")?)?,
+        sell_signature: sell_signature
+            .as_deref()
+            .map(Signature::from_str)
+            .transpose()?,
+        price_checks: row.get("price_checks")?,
+        status: row.get::<_, String>("status")?.parse()?,
+        opened_at_ms: row.get::<_, i64>("opened_at_ms")? as u64,
+        closed_at_ms: row
+            .get::<_, Option<i64>>("closed_at_ms")?
+            .map(|closed_at_ms| closed_at_ms as u64),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position() -> Position {
+        let mint = Pubkey::new_unique();
+        Position {
+            pool: PoolInitTxInfos {
+                amm_id: Pubkey::new_unique(),
+                market_id: Pubkey::new_unique(),
+                base_mint: mint,
+                quote_mint: Pubkey::new_unique(),
+            },
+            mint,
+            wallet: Pubkey::new_unique(),
+            entry_lamports: 1_000_000_000,
+            entry_tokens: None,
+            entry_price: None,
+            buy_signature: Signature::new_unique(),
+            sell_signature: None,
+            price_checks: 0,
+            status: PositionStatus::Open,
+            opened_at_ms: 1_000,
+            closed_at_ms: None,
+        }
+    }
+
+    #[test]
+    fn status_round_trips_through_its_string() {
+        for status in [
+            PositionStatus::Open,
+            PositionStatus::Sold,
+            PositionStatus::Expired,
+        ] {
+            assert_eq!(status.as_str().parse::<PositionStatus>().unwrap(), status);
+        }
+        assert_eq!(PositionStatus::Sold.as_str(), "sold");
+        assert!("closed".parse::<PositionStatus>().is_err());
+    }
+
+    #[test]
+    fn tracks_a_position_until_closed() {
+        let store = PositionStore::open(Path::new(":memory:")).unwrap();
+        let position = position();
+        let amm_id = position.pool.amm_id;
+
+        store.insert(&position).unwrap();
+        let open_positions = store.open_positions().unwrap();
+        assert_eq!(open_positions.len(), 1);
+        assert_eq!(open_positions[0].pool.amm_id, amm_id);
+        assert_eq!(open_positions[0].pool.market_id, position.pool.market_id);
+        assert_eq!(open_positions[0].mint, position.mint);
+        assert_eq!(open_positions[0].wallet, position.wallet);
+        assert_eq!(open_positions[0].buy_signature, position.buy_signature);
+        assert_eq!(open_positions[0].status, PositionStatus::Open);
+        assert_eq!(open_positions[0].entry_tokens, None);
+
+        // The entry is only recorded on the first price check
+        store
+            .record_price_check(&amm_id, 1, 5_000, 200_000.)
+            .unwrap();
+        store
+            .record_price_check(&amm_id, 2, 6_000, 300_000.)
+            .unwrap();
+        let open_positions = store.open_positions().unwrap();
+        assert_eq!(open_positions[0].price_checks, 2);
+        assert_eq!(open_positions[0].entry_tokens, Some(5_000));
+        assert_eq!(open_positions[0].entry_price, Some(200_000.));
+
+        let sell_signature = Signature::new_unique();
+        store
+            .close(&amm_id, PositionStatus::Sold, Some(sell_signature))
+            .unwrap();
+        assert!(store.open_positions().unwrap().is_empty());
+
+        let status: String = store
+            .connection
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT status FROM positions WHERE amm_id = ?1",
+                params![amm_id.to_string()],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(status, "sold");
+    }
+
+    #[test]
+    fn open_positions_are_ordered_by_opening() {
+        let store = PositionStore::open(Path::new(":memory:")).unwrap();
+        let later = Position {
+            opened_at_ms: 2_000,
+            ..position()
+        };
+        let earlier = position();
+        let expired = position();
+        store.insert(&later).unwrap();
+        store.insert(&earlier).unwrap();
+        store.insert(&expired).unwrap();
+        store
+            .close(&expired.pool.amm_id, PositionStatus::Expired, None)
+            .unwrap();
+
+        let amm_ids: Vec<Pubkey> = store
+            .open_positions()
+            .unwrap()
+            .into_iter()
+            .map(|position| position.pool.amm_id)
+            .collect();
+        assert_eq!(amm_ids, vec![earlier.pool.amm_id, later.pool.amm_id]);
+    }
+}
@@ -0,0 +1,268 @@
+use serde::Serialize;
+
+use crate::{
+    actors::swapper::strategy::Strategy,
+    constants::LAMPORTS_PER_SOL,
+    recording::replay::{ReplayTrade, TradeOutcome},
+};
+
+/// Aggregated results of one filter and exit configuration, one CSV row
+#[derive(Debug, Clone, Serialize)]
+pub struct BacktestStats {
+    pub take_profit_multiple: f64,
+    pub min_liquidity_sol: f64,
+    pub max_liquidity_sol: f64,
+    pub max_price_checks: u32,
+    pub pools: usize,
+    pub filtered: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub entered: usize,
+    pub took_profit: usize,
+    pub expired: usize,
+    pub win_rate_pct: f64,
+    pub average_return_pct: f64,
+    pub median_return_pct: f64,
+    pub pnl_sol: f64,
+    /// Largest drop of the cumulative PnL from a previous high
+    pub max_drawdown_sol: f64,
+    /// Time in trade percentiles of the entered positions, in seconds
+    pub time_in_trade_p50_s: f64,
+    pub time_in_trade_p90_s: f64,
+    pub time_in_trade_max_s: f64,
+}
+
+impl BacktestStats {
+    pub fn from_trades(strategy: &Strategy, trades: &[ReplayTrade]) -> Self {
+        let count = |outcome: fn(&TradeOutcome) -> bool| {
+            trades
+                .iter()
+                .filter(|trade| outcome(&trade.outcome))
+                .count()
+        };
+
+        let mut entered: Vec<&ReplayTrade> =
+            trades.iter().filter(|trade| trade.is_entered()).collect();
+        entered.sort_by_key(|trade| trade.exit_at_ms.or(trade.entry_at_ms));
+
+        let mut returns: Vec<f64> = entered.iter().map(|trade| trade.return_pct()).collect();
+        returns.sort_by(f64::total_cmp);
+        let mut times_in_trade: Vec<f64> = entered
+            .iter()
+            .filter_map(|trade| {
+                let entry_at_ms = trade.entry_at_ms?;
+                let exit_at_ms = trade.exit_at_ms.unwrap_or(entry_at_ms);
+                Some(exit_at_ms.saturating_sub(entry_at_ms) as f64 / 1_000.)
+            })
+            .collect();
+        times_in_trade.sort_by(f64::total_cmp);
+
+        let mut pnl_lamports = 0;
+        let mut peak_pnl_lamports = 0;
+        let mut max_drawdown_lamports = 0;
+        for trade in &entered {
+            pnl_lamports += trade.pnl_lamports();
+            peak_pnl_lamports = peak_pnl_lamports.max(pnl_lamports);
+            max_drawdown_lamports = max_drawdown_lamports.max(peak_pnl_lamports - pnl_lamports);
+        }
+
+        let wins = entered
+            .iter()
+            .filter(|trade| trade.pnl_lamports() > 0)
+            .count();
+
+        Self {
+            take_profit_multiple: strategy.take_profit_multiple,
+            min_liquidity_sol: strategy.min_liquidity as f64 / *LAMPORTS_PER_SOL,
+            max_liquidity_sol: strategy.max_liquidity as f64 / *LAMPORTS_PER_SOL,
+            max_price_checks: strategy.max_price_checks,
+            pools: trades.len(),
+            filtered: count(|outcome| matches!(outcome, TradeOutcome::Filtered(_))),
+            skipped: count(|outcome| *outcome == TradeOutcome::Skipped),
+            failed: count(|outcome| matches!(outcome, TradeOutcome::Failed(_))),
+            entered: entered.len(),
+            took_profit: count(|outcome| *outcome == TradeOutcome::TookProfit),
+            expired: count(|outcome| *outcome == TradeOutcome::Expired),
+            win_rate_pct: ratio_pct(wins, entered.len()),
+            average_return_pct: if returns.is_empty() {
+                0.
+            } else {
+                returns.iter().sum::<f64>() / returns.len() as f64
+            },
+            median_return_pct: percentile(&returns, 50.),
+            pnl_sol: pnl_lamports as f64 / *LAMPORTS_PER_SOL,
+            max_drawdown_sol: max_drawdown_lamports as f64 / *LAMPORTS_PER_SOL,
+            time_in_trade_p50_s: percentile(&times_in_trade, 50.),
+            time_in_trade_p90_s: percentile(&times_in_trade, 90.),
+            time_in_trade_max_s: times_in_trade.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+fn ratio_pct(count: usize, total: usize) -> f64 {
+    if total == 0 {
+        return 0.;
+    }
+    count as f64 / total as f64 * 100.
+}
+
+/// Nearest rank percentile of sorted values, 0 when empty
+fn percentile(sorted: &[f64], pct: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.;
+    }
+    let rank = (pct / 100. * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::*;
+
+    const LAMPORTS_IN: u64 = 1_000_000_000;
+
+    fn entered_trade(
+        pnl_lamports: i64,
+        entry_at_ms: u64,
+        exit_at_ms: Option<u64>,
+        outcome: TradeOutcome,
+    ) -> ReplayTrade {
+        ReplayTrade {
+            amm_id: Pubkey::new_unique(),
+            mint: Some(Pubkey::new_unique()),
+            detected_at_ms: entry_at_ms,
+            entry_at_ms: Some(entry_at_ms),
+            exit_at_ms,
+            lamports_in: LAMPORTS_IN,
+            lamports_out: (LAMPORTS_IN as i64 + pnl_lamports) as u64,
+            outcome,
+        }
+    }
+
+    fn not_entered_trade(outcome: TradeOutcome) -> ReplayTrade {
+        ReplayTrade {
+            amm_id: Pubkey::new_unique(),
+            mint: None,
+            detected_at_ms: 0,
+            entry_at_ms: None,
+            exit_at_ms: None,
+            lamports_in: 0,
+            lamports_out: 0,
+            outcome,
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn empty_replay_has_zero_stats() {
+        let stats = BacktestStats::from_trades(&Strategy::default(), &[]);
+
+        assert_eq!(stats.pools, 0);
+        assert_eq!(stats.entered, 0);
+        assert_close(stats.win_rate_pct, 0.);
+        assert_close(stats.average_return_pct, 0.);
+        assert_close(stats.median_return_pct, 0.);
+        assert_close(stats.pnl_sol, 0.);
+        assert_close(stats.max_drawdown_sol, 0.);
+        assert_close(stats.time_in_trade_p90_s, 0.);
+        assert_close(stats.time_in_trade_max_s, 0.);
+    }
+
+    #[test]
+    fn single_winning_trade() {
+        let trades = [entered_trade(
+            500_000_000,
+            1_000,
+            Some(4_000),
+            TradeOutcome::TookProfit,
+        )];
+
+        let stats = BacktestStats::from_trades(&Strategy::default(), &trades);
+
+        assert_eq!(stats.entered, 1);
+        assert_eq!(stats.took_profit, 1);
+        assert_close(stats.win_rate_pct, 100.);
+        assert_close(stats.average_return_pct, 50.);
+        assert_close(stats.median_return_pct, 50.);
+        assert_close(stats.pnl_sol, 0.5);
+        assert_close(stats.max_drawdown_sol, 0.);
+        assert_close(stats.time_in_trade_p50_s, 3.);
+        assert_close(stats.time_in_trade_p90_s, 3.);
+    }
+
+    #[test]
+    fn all_losses_draw_down_from_zero() {
+        let trades = [
+            entered_trade(-200_000_000, 0, Some(2_000), TradeOutcome::Expired),
+            entered_trade(-300_000_000, 0, Some(3_000), TradeOutcome::Expired),
+        ];
+
+        let stats = BacktestStats::from_trades(&Strategy::default(), &trades);
+
+        assert_eq!(stats.expired, 2);
+        assert_close(stats.win_rate_pct, 0.);
+        assert_close(stats.average_return_pct, -25.);
+        // Nearest rank of an even count is the lower value
+        assert_close(stats.median_return_pct, -30.);
+        assert_close(stats.pnl_sol, -0.5);
+        assert_close(stats.max_drawdown_sol, 0.5);
+    }
+
+    #[test]
+    fn counts_open_positions_and_orders_drawdown_by_exit() {
+        let trades = [
+            not_entered_trade(TradeOutcome::Filtered("liquidity".into())),
+            // Listed out of exit order on purpose
+            entered_trade(-100_000_000, 2_500, Some(3_000), TradeOutcome::Expired),
+            entered_trade(500_000_000, 0, Some(1_000), TradeOutcome::TookProfit),
+            not_entered_trade(TradeOutcome::Skipped),
+            entered_trade(-300_000_000, 1_000, Some(2_000), TradeOutcome::Expired),
+            // Never checked, ordered and timed by its entry
+            entered_trade(200_000_000, 4_000, None, TradeOutcome::Open),
+            not_entered_trade(TradeOutcome::Failed("pool not recorded".into())),
+        ];
+
+        let stats = BacktestStats::from_trades(&Strategy::default(), &trades);
+
+        assert_eq!(stats.pools, 7);
+        assert_eq!(stats.filtered, 1);
+        assert_eq!(stats.skipped, 1);
+        assert_eq!(stats.failed, 1);
+        assert_eq!(stats.entered, 4);
+        assert_close(stats.win_rate_pct, 50.);
+        assert_close(stats.average_return_pct, 7.5);
+        assert_close(stats.pnl_sol, 0.3);
+        // Cumulative PnL goes 0.5, 0.2, 0.1, 0.3
+        assert_close(stats.max_drawdown_sol, 0.4);
+        // Times in trade are 0, 0.5, 1 and 1 seconds
+        assert_close(stats.time_in_trade_p50_s, 0.5);
+        assert_close(stats.time_in_trade_max_s, 1.);
+    }
+
+    #[test]
+    fn percentile_uses_nearest_rank() {
+        let sorted = [1., 2., 3., 4.];
+
+        assert_close(percentile(&sorted, 0.), 1.);
+        assert_close(percentile(&sorted, 50.), 2.);
+        assert_close(percentile(&sorted, 90.), 4.);
+        assert_close(percentile(&sorted, 100.), 4.);
+        assert_close(percentile(&[], 50.), 0.);
+    }
+
+    #[test]
+    fn ratio_pct_of_nothing_is_zero() {
+        assert_close(ratio_pct(0, 0), 0.);
+        assert_close(ratio_pct(1, 4), 25.);
+    }
+}
@@ -0,0 +1,160 @@
+pub mod backtest;
+pub mod recorder;
+pub mod replay;
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, ErrorKind, Lines},
+    path::Path,
+};
+
+use flate2::read::MultiGzDecoder;
+use serde::{Deserialize, Serialize};
+use solana_sdk::{account::Account, pubkey::Pubkey, signature::Signature};
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+
+use crate::websocket::LogsSubscribeResponse;
+
+/// Event captured by `listen --record`, stored as one JSON object per line
+/// of a gzip file
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RecordedEvent {
+    /// Log notification mentioning the pool creation fee account
+    Log {
+        at_ms: u64,
+        log: LogsSubscribeResponse,
+    },
+    /// Pool creation transaction fetched for a log
+    Transaction {
+        at_ms: u64,
+        signature: Signature,
+        transaction: Box<EncodedConfirmedTransactionWithStatusMeta>,
+    },
+    /// Pool and mint accounts of a detected pool
+    Pool {
+        at_ms: u64,
+        amm_id: Pubkey,
+        epoch: u64,
+        pool: Account,
+        base_mint: Account,
+        quote_mint: Account,
+    },
+    /// Vault balances of a detected pool, taken every price check interval
+    VaultSnapshot {
+        at_ms: u64,
+        amm_id: Pubkey,
+        base_vault_amount: u64,
+        quote_vault_amount: u64,
+    },
+}
+
+impl RecordedEvent {
+    pub fn at_ms(&self) -> u64 {
+        match self {
+            RecordedEvent::Log { at_ms, .. }
+            | RecordedEvent::Transaction { at_ms, .. }
+            | RecordedEvent::Pool { at_ms, .. }
+            | RecordedEvent::VaultSnapshot { at_ms, .. } => *at_ms,
+        }
+    }
+}
+
+/// Reads the events of a recording back in order. A recording cut short by
+/// a crash ends at its last complete event.
+pub struct RecordingReader {
+    lines: Lines<BufReader<MultiGzDecoder<File>>>,
+}
+
+impl RecordingReader {
+    pub fn open(path: &Path) -> Result<Self, eyre::Error> {
+        let file = File::open(path)?;
+        Ok(Self {
+            lines: BufReader::new(MultiGzDecoder::new(file)).lines(),
+        })
+    }
+}
+
+impl Iterator for RecordingReader {
+    type Item = Result<RecordedEvent, eyre::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.lines.next()? {
+            Ok(line) => Some(serde_json::from_str(&line).map_err(Into::into)),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                tracing::warn!("recording is truncated, stopping at the last complete event");
+                None
+            }
+            Err(e) => Some(Err(e.into())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording::recorder::Recorder;
+
+    #[test]
+    fn reads_back_recorded_events_in_order() {
+        let path = std::env::temp_dir().join(format!(
+            "solana-buyer-recording-{}-{}.gz",
+            std::process::id(),
+            Pubkey::new_unique()
+        ));
+        let amm_id = Pubkey::new_unique();
+        let pool = Account {
+            lamports: 1_000,
+            data: vec![1, 2, 3],
+            owner: Pubkey::new_unique(),
+            executable: false,
+            rent_epoch: 0,
+        };
+
+        let recorder = Recorder::create(&path).unwrap();
+        recorder.record(&RecordedEvent::Pool {
+            at_ms: 1,
+            amm_id,
+            epoch: 600,
+            pool: pool.clone(),
+            base_mint: Account::default(),
+            quote_mint: Account::default(),
+        });
+        for at_ms in 2..4 {
+            recorder.record(&RecordedEvent::VaultSnapshot {
+                at_ms,
+                amm_id,
+                base_vault_amount: at_ms * 10,
+                quote_vault_amount: at_ms * 100,
+            });
+        }
+        recorder.finish().unwrap();
+
+        let events = RecordingReader::open(&path)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(events.len(), 3);
+        assert!(matches!(
+            &events[0],
+            RecordedEvent::Pool { at_ms: 1, amm_id: id, epoch: 600, pool: recorded, .. }
+                if *id == amm_id && *recorded == pool
+        ));
+        for (event, at_ms) in events[1..].iter().zip(2..) {
+            assert!(matches!(
+                event,
+                RecordedEvent::VaultSnapshot {
+                    at_ms: recorded_at_ms,
+                    amm_id: id,
+                    base_vault_amount,
+                    quote_vault_amount,
+                } if *recorded_at_ms == at_ms
+                    && *id == amm_id
+                    && *base_vault_amount == at_ms * 10
+                    && *quote_vault_amount == at_ms * 100
+            ));
+        }
+    }
+}
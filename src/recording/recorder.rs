@@ -0,0 +1,131 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use borsh::BorshDeserialize;
+use eyre::OptionExt;
+use flate2::{write::GzEncoder, Compression};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use tracing::Instrument;
+
+use crate::{
+    actors::swapper::{actor::PoolInitTxInfos, strategy::PRICE_CHECK_INTERVAL},
+    recording::RecordedEvent,
+    types::PoolInfo,
+    utils::{get_epoch, get_token_accounts, now_ms},
+};
+
+/// Vault snapshots taken per detected pool, about twenty minutes worth
+pub const RECORDED_PRICE_CHECKS: usize = 400;
+
+/// Appends events to a gzip compressed recording, see [`RecordedEvent`]
+pub struct Recorder {
+    writer: Mutex<GzEncoder<BufWriter<File>>>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> Result<Self, eyre::Error> {
+        let file = File::create(path)?;
+        Ok(Self {
+            writer: Mutex::new(GzEncoder::new(BufWriter::new(file), Compression::default())),
+        })
+    }
+
+    /// Appends the event, flushed right away so a crash only loses the
+    /// event being written
+    pub fn record(&self, event: &RecordedEvent) {
+        let mut writer = self.writer.lock().unwrap();
+        let result = serde_json::to_writer(&mut *writer, event)
+            .map_err(eyre::Error::from)
+            .and_then(|_| Ok(writer.write_all(b"\n")?))
+            .and_then(|_| Ok(writer.flush()?));
+        if let Err(e) = result {
+            tracing::error!("failed to record event: {:?}", e);
+        }
+    }
+
+    /// Records the pool and mint accounts, then snapshots the pool vaults
+    /// every price check interval in the background
+    pub fn track_pool(
+        self: &Arc<Self>,
+        client: Arc<RpcClient>,
+        pool_init_tx_infos: PoolInitTxInfos,
+    ) {
+        let recorder = Arc::clone(self);
+        tokio::spawn(
+            async move {
+                if let Err(e) = recorder.record_pool(&client, pool_init_tx_infos).await {
+                    tracing::error!(
+                        "failed to record pool {}: {:?}",
+                        pool_init_tx_infos.amm_id,
+                        e
+                    );
+                }
+            }
+            .in_current_span(),
+        );
+    }
+
+    /// Writes the gzip trailer, events recorded afterwards are lost
+    pub fn finish(&self) -> Result<(), eyre::Error> {
+        Ok(self.writer.lock().unwrap().try_finish()?)
+    }
+
+    async fn record_pool(
+        &self,
+        client: &RpcClient,
+        pool_init_tx_infos: PoolInitTxInfos,
+    ) -> Result<(), eyre::Error> {
+        let amm_id = pool_init_tx_infos.amm_id;
+        let mut accounts = client
+            .get_multiple_accounts(&[
+                amm_id,
+                pool_init_tx_infos.base_mint,
+                pool_init_tx_infos.quote_mint,
+            ])
+            .await?
+            .into_iter();
+        let pool = accounts
+            .next()
+            .flatten()
+            .ok_or_eyre("pool account not found")?;
+        let base_mint = accounts
+            .next()
+            .flatten()
+            .ok_or_eyre("base mint account not found")?;
+        let quote_mint = accounts
+            .next()
+            .flatten()
+            .ok_or_eyre("quote mint account not found")?;
+        let pool_info = PoolInfo::deserialize(&mut &pool.data[..])?;
+        let epoch = get_epoch(client).await?;
+
+        self.record(&RecordedEvent::Pool {
+            at_ms: now_ms(),
+            amm_id,
+            epoch,
+            pool,
+            base_mint,
+            quote_mint,
+        });
+
+        for _ in 0..RECORDED_PRICE_CHECKS {
+            match get_token_accounts(client, &[pool_info.base_vault, pool_info.quote_vault]).await {
+                // safe to unwrap, because `[get_token_accounts]` checks that returned
+                // vector length matches the input vector length
+                Ok(vaults) => self.record(&RecordedEvent::VaultSnapshot {
+                    at_ms: now_ms(),
+                    amm_id,
+                    base_vault_amount: vaults.first().unwrap().amount,
+                    quote_vault_amount: vaults.get(1).unwrap().amount,
+                }),
+                Err(e) => tracing::debug!("failed to snapshot vaults of {}: {:?}", amm_id, e),
+            }
+            tokio::time::sleep(PRICE_CHECK_INTERVAL).await;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,471 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    time::Duration,
+};
+
+use borsh::BorshDeserialize;
+use serde::Serialize;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+use crate::{
+    actors::{
+        listener::utils::{get_initialize2_signature, get_pool_init_infos_from_transaction},
+        swapper::strategy::{get_position_price, Strategy},
+    },
+    constants::{LAMPORTS_PER_SOL, SOL},
+    recording::{recorder::RECORDED_PRICE_CHECKS, RecordedEvent, RecordingReader},
+    sender::paper::{PaperBook, PaperEngine},
+    types::{MintInfo, PoolInfo},
+    utils::get_swap_amount_out,
+};
+
+/// Parameters of a replay
+#[derive(Debug, Clone, Copy)]
+pub struct ReplayConfig {
+    pub strategy: Strategy,
+    /// SOL spent per position
+    pub trade_amount: f64,
+    /// Pools traded at the same time, counted from their detection like the
+    /// listener counts its swappers, further pools are skipped
+    pub max_swappers: usize,
+    /// Starting virtual SOL balance
+    pub paper_sol: f64,
+}
+
+/// How a replayed pool ended
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TradeOutcome {
+    /// Rejected by the entry filters
+    Filtered(String),
+    /// Detected while `max_swappers` swappers were running
+    Skipped,
+    /// The simulated buy or sell failed
+    Failed(String),
+    /// Sold at the take profit
+    TookProfit,
+    /// Given up on after the last price check, valued at the last snapshot
+    Expired,
+    /// Still open when the recording ended, valued at the last snapshot
+    Open,
+}
+
+/// Result of a replayed pool. Amounts are in lamports, `lamports_out` is
+/// the sale proceeds or the value at the last snapshot for unsold positions.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayTrade {
+    pub amm_id: Pubkey,
+    pub mint: Option<Pubkey>,
+    pub detected_at_ms: u64,
+    pub entry_at_ms: Option<u64>,
+    pub exit_at_ms: Option<u64>,
+    pub lamports_in: u64,
+    pub lamports_out: u64,
+    pub outcome: TradeOutcome,
+}
+
+impl ReplayTrade {
+    pub fn pnl_lamports(&self) -> i64 {
+        self.lamports_out as i64 - self.lamports_in as i64
+    }
+
+    /// Return on the SOL spent, zero for pools never entered
+    pub fn return_pct(&self) -> f64 {
+        if self.lamports_in == 0 {
+            return 0.;
+        }
+        self.pnl_lamports() as f64 / self.lamports_in as f64 * 100.
+    }
+
+    pub fn is_entered(&self) -> bool {
+        self.entry_at_ms.is_some()
+    }
+}
+
+struct ReplayPool {
+    trade: ReplayTrade,
+    pool: Option<(PoolInfo, MintInfo, MintInfo, u64)>,
+    /// Target tokens held once entered
+    tokens: u64,
+    price_checks: u32,
+}
+
+/// Feeds recorded events through the listener pool detection, the swapper
+/// entry filters and exit strategy, filling trades with a [`PaperEngine`]
+/// at the recorded vault balances.
+pub struct Replay {
+    config: ReplayConfig,
+    paper_engine: PaperEngine,
+    detected: HashSet<Signature>,
+    pools: HashMap<Pubkey, ReplayPool>,
+    trades: Vec<ReplayTrade>,
+}
+
+impl Replay {
+    pub fn new(config: ReplayConfig) -> Self {
+        Self {
+            config,
+            paper_engine: PaperEngine::new((config.paper_sol * *LAMPORTS_PER_SOL) as u64),
+            detected: HashSet::new(),
+            pools: HashMap::new(),
+            trades: vec![],
+        }
+    }
+
+    pub async fn process(&mut self, event: RecordedEvent) {
+        match event {
+            RecordedEvent::Log { log, .. } => {
+                if let Ok(signature) = get_initialize2_signature(log) {
+                    self.detected.insert(signature);
+                }
+            }
+            RecordedEvent::Transaction {
+                at_ms,
+                signature,
+                transaction,
+            } => {
+                if !self.detected.remove(&signature) {
+                    return;
+                }
+                match get_pool_init_infos_from_transaction(&transaction).await {
+                    Ok(pool_init_tx_infos) => {
+                        self.on_pool_detected(pool_init_tx_infos.amm_id, at_ms)
+                    }
+                    Err(e) => tracing::debug!("error with transaction: {:?}", e),
+                }
+            }
+            RecordedEvent::Pool {
+                amm_id,
+                epoch,
+                pool,
+                base_mint,
+                quote_mint,
+                ..
+            } => {
+                let Some(replay_pool) = self.pools.get_mut(&amm_id) else {
+                    return;
+                };
+                let decoded = PoolInfo::deserialize(&mut &pool.data[..])
+                    .map_err(eyre::Error::from)
+                    .and_then(|pool_info| {
+                        Ok((
+                            MintInfo::from_account(pool_info.base_mint, &base_mint)?,
+                            MintInfo::from_account(pool_info.quote_mint, &quote_mint)?,
+                            pool_info,
+                        ))
+                    });
+                match decoded {
+                    Ok((base_mint_info, quote_mint_info, pool_info))
+                        if base_mint_info.mint == *SOL || quote_mint_info.mint == *SOL =>
+                    {
+                        replay_pool.trade.mint = Some(if base_mint_info.mint == *SOL {
+                            quote_mint_info.mint
+                        } else {
+                            base_mint_info.mint
+                        });
+                        replay_pool.pool =
+                            Some((pool_info, base_mint_info, quote_mint_info, epoch));
+                    }
+                    Ok(_) => self.close(&amm_id, TradeOutcome::Filtered("not a SOL pair".into())),
+                    Err(e) => self.close(&amm_id, TradeOutcome::Failed(e.to_string())),
+                }
+            }
+            RecordedEvent::VaultSnapshot {
+                at_ms,
+                amm_id,
+                base_vault_amount,
+                quote_vault_amount,
+            } => self.on_vault_snapshot(&amm_id, at_ms, (base_vault_amount, quote_vault_amount)),
+        }
+    }
+
+    /// Trades of every replayed pool, positions still open are valued at
+    /// their last snapshot, and the final virtual balances
+    pub fn finish(mut self) -> (Vec<ReplayTrade>, PaperBook) {
+        for (_, replay_pool) in self.pools.drain() {
+            let outcome = if replay_pool.trade.is_entered() {
+                TradeOutcome::Open
+            } else if replay_pool.pool.is_none() {
+                // The live run failed to fetch the pool accounts
+                TradeOutcome::Failed("pool not recorded".into())
+            } else {
+                TradeOutcome::Failed("no vault snapshot recorded".into())
+            };
+            self.trades.push(ReplayTrade {
+                outcome,
+                ..replay_pool.trade
+            });
+        }
+        self.trades.sort_by_key(|trade| trade.detected_at_ms);
+        (self.trades, self.paper_engine.book())
+    }
+
+    fn on_pool_detected(&mut self, amm_id: Pubkey, at_ms: u64) {
+        let trade = ReplayTrade {
+            amm_id,
+            mint: None,
+            detected_at_ms: at_ms,
+            entry_at_ms: None,
+            exit_at_ms: None,
+            lamports_in: 0,
+            lamports_out: 0,
+            outcome: TradeOutcome::Open,
+        };
+        // Like the listener counting its supervised swappers, a pool holds a
+        // slot from its detection, entered or not
+        if self.pools.len() >= self.config.max_swappers {
+            self.trades.push(ReplayTrade {
+                outcome: TradeOutcome::Skipped,
+                ..trade
+            });
+            return;
+        }
+        self.pools.insert(
+            amm_id,
+            ReplayPool {
+                trade,
+                pool: None,
+                tokens: 0,
+                price_checks: 0,
+            },
+        );
+    }
+
+    fn on_vault_snapshot(&mut self, amm_id: &Pubkey, at_ms: u64, vault_amounts: (u64, u64)) {
+        let strategy = self.config.strategy;
+        let trade_lamports = (self.config.trade_amount * *LAMPORTS_PER_SOL) as u64;
+        let Some(replay_pool) = self.pools.get_mut(amm_id) else {
+            return;
+        };
+        let Some((pool_info, base_mint_info, quote_mint_info, epoch)) = &replay_pool.pool else {
+            return;
+        };
+        let (sol_mint_info, target_mint_info, sol_vault_amount, target_vault_amount) =
+            if base_mint_info.mint == *SOL {
+                (
+                    base_mint_info,
+                    quote_mint_info,
+                    vault_amounts.0,
+                    vault_amounts.1,
+                )
+            } else {
+                (
+                    quote_mint_info,
+                    base_mint_info,
+                    vault_amounts.1,
+                    vault_amounts.0,
+                )
+            };
+
+        // The first snapshot is taken when the swapper would have started
+        if !replay_pool.trade.is_entered() {
+            if !strategy.is_liquidity_in_bounds(sol_vault_amount) {
+                let reason = format!("liquidity not in bound: {}", sol_vault_amount);
+                self.close(amm_id, TradeOutcome::Filtered(reason));
+                return;
+            }
+            match self.paper_engine.fill_at_reserves(
+                pool_info,
+                (sol_mint_info, target_mint_info),
+                *epoch,
+                vault_amounts,
+                trade_lamports,
+                // A buy filling no tokens fails without debiting the book,
+                // the position would have no price
+                1,
+            ) {
+                Ok((tokens, _)) => {
+                    replay_pool.tokens = tokens;
+                    replay_pool.trade.entry_at_ms = Some(at_ms);
+                    replay_pool.trade.lamports_in = trade_lamports;
+                }
+                Err(e) => self.close(amm_id, TradeOutcome::Failed(e.to_string())),
+            }
+            return;
+        }
+
+        let sell_transfer_fee = target_mint_info.transfer_fee(replay_pool.tokens, *epoch);
+        let buy_price = trade_lamports as f64 / replay_pool.tokens as f64;
+        let current_price = get_position_price(
+            replay_pool.tokens,
+            sell_transfer_fee,
+            sol_vault_amount,
+            target_vault_amount,
+        );
+        replay_pool.trade.exit_at_ms = Some(at_ms);
+        replay_pool.trade.lamports_out = get_swap_amount_out(
+            pool_info,
+            vault_amounts.0,
+            vault_amounts.1,
+            replay_pool.tokens.saturating_sub(sell_transfer_fee),
+            pool_info.base_mint == target_mint_info.mint,
+        );
+
+        if strategy.should_take_profit(buy_price, current_price) {
+            let outcome = match self.paper_engine.fill_at_reserves(
+                pool_info,
+                (target_mint_info, sol_mint_info),
+                *epoch,
+                vault_amounts,
+                replay_pool.tokens,
+                0,
+            ) {
+                Ok((lamports, _)) => {
+                    replay_pool.trade.lamports_out = lamports;
+                    TradeOutcome::TookProfit
+                }
+                Err(e) => TradeOutcome::Failed(e.to_string()),
+            };
+            self.close(amm_id, outcome);
+            return;
+        }
+
+        if strategy.is_expired(replay_pool.price_checks) {
+            self.close(amm_id, TradeOutcome::Expired);
+            return;
+        }
+        replay_pool.price_checks += 1;
+    }
+
+    fn close(&mut self, amm_id: &Pubkey, outcome: TradeOutcome) {
+        if let Some(replay_pool) = self.pools.remove(amm_id) {
+            self.trades.push(ReplayTrade {
+                outcome,
+                ..replay_pool.trade
+            });
+        }
+    }
+}
+
+/// Replays the recordings, waiting between events at `speed` times the
+/// recorded pace unless it is 0
+pub async fn replay_recordings(
+    recordings: &[PathBuf],
+    config: ReplayConfig,
+    speed: f64,
+) -> Result<Vec<ReplayTrade>, eyre::Error> {
+    // The entry takes the first snapshot, the position expires on price
+    // check `max_price_checks + 1`
+    let needed_snapshots = config.strategy.max_price_checks as usize + 3;
+    if needed_snapshots > RECORDED_PRICE_CHECKS {
+        tracing::warn!(
+            "recordings hold {} vault snapshots per pool, positions can't expire after {} price checks and end as open",
+            RECORDED_PRICE_CHECKS,
+            config.strategy.max_price_checks
+        );
+    }
+
+    let mut replay = Replay::new(config);
+    let mut last_at_ms = None;
+    for path in recordings {
+        for event in RecordingReader::open(path)? {
+            let event = event?;
+            if let Some(last_at_ms) = last_at_ms.filter(|_| speed > 0.) {
+                let wait_ms = event.at_ms().saturating_sub(last_at_ms) as f64 / speed;
+                tokio::time::sleep(Duration::from_millis(wait_ms as u64)).await;
+            }
+            last_at_ms = Some(event.at_ms());
+            replay.process(event).await;
+        }
+    }
+
+    let (trades, book) = replay.finish();
+    tracing::info!(
+        "replay done, virtual balance {} SOL and {} token positions",
+        book.lamports as f64 / *LAMPORTS_PER_SOL,
+        book.tokens.values().filter(|amount| **amount > 0).count()
+    );
+    Ok(trades)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::TOKEN_PROGRAM;
+
+    fn replay(max_swappers: usize) -> Replay {
+        Replay::new(ReplayConfig {
+            strategy: Strategy::default(),
+            trade_amount: 1.,
+            max_swappers,
+            paper_sol: 10.,
+        })
+    }
+
+    fn mint_info(mint: Pubkey) -> MintInfo {
+        MintInfo {
+            mint,
+            token_program: *TOKEN_PROGRAM,
+            transfer_fee_config: None,
+        }
+    }
+
+    /// Detects a pool trading a new token against SOL, as if its accounts
+    /// were recorded
+    fn detect_recorded_pool(replay: &mut Replay, amm_id: Pubkey) {
+        let token = mint_info(Pubkey::new_unique());
+        let pool_info = PoolInfo {
+            base_mint: token.mint,
+            quote_mint: *SOL,
+            swap_fee_numerator: 25,
+            swap_fee_denominator: 10_000,
+            ..PoolInfo::default()
+        };
+        replay.on_pool_detected(amm_id, 0);
+        replay.pools.get_mut(&amm_id).unwrap().pool = Some((pool_info, token, mint_info(*SOL), 0));
+    }
+
+    #[test]
+    fn reports_pool_without_recorded_accounts_as_failed() {
+        let mut replay = replay(1);
+        replay.on_pool_detected(Pubkey::new_unique(), 0);
+
+        let (trades, _) = replay.finish();
+
+        assert_eq!(trades.len(), 1);
+        assert_eq!(
+            trades[0].outcome,
+            TradeOutcome::Failed("pool not recorded".into())
+        );
+    }
+
+    #[test]
+    fn pending_pool_holds_a_swapper_slot() {
+        let mut replay = replay(1);
+        let pending = Pubkey::new_unique();
+        let skipped = Pubkey::new_unique();
+        replay.on_pool_detected(pending, 0);
+        replay.on_pool_detected(skipped, 1);
+
+        // Closing the pending pool frees its slot
+        replay.close(&pending, TradeOutcome::Filtered("test".into()));
+        let entered = Pubkey::new_unique();
+        detect_recorded_pool(&mut replay, entered);
+        replay.on_vault_snapshot(&entered, 2, (1_000_000_000, 50_000_000_000));
+
+        let (trades, _) = replay.finish();
+        let outcome = |amm_id| {
+            trades
+                .iter()
+                .find(|trade| trade.amm_id == amm_id)
+                .map(|trade| trade.outcome.clone())
+        };
+        assert_eq!(outcome(skipped), Some(TradeOutcome::Skipped));
+        assert_eq!(outcome(entered), Some(TradeOutcome::Open));
+    }
+
+    #[test]
+    fn buy_filling_no_tokens_fails_without_debiting() {
+        let mut replay = replay(1);
+        let amm_id = Pubkey::new_unique();
+        detect_recorded_pool(&mut replay, amm_id);
+
+        replay.on_vault_snapshot(&amm_id, 1, (0, 50_000_000_000));
+
+        assert_eq!(replay.paper_engine.balance(&SOL), 10_000_000_000);
+        let (trades, _) = replay.finish();
+        assert!(matches!(trades[0].outcome, TradeOutcome::Failed(_)));
+        assert!(!trades[0].is_entered());
+    }
+}
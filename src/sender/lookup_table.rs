@@ -0,0 +1,66 @@
+use std::str::FromStr;
+
+use eyre::{eyre, Result};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    address_lookup_table::{state::AddressLookupTable, AddressLookupTableAccount},
+    compute_budget,
+    pubkey::Pubkey,
+    system_program,
+};
+
+use crate::{
+    constants::{AMM_V4, OPENBOOK, RAYDIUM_AUTHORITY_V4, SOL, TOKEN_2022_PROGRAM, TOKEN_PROGRAM},
+    types::ProgramConfig,
+};
+
+/// Accounts referenced by every swap regardless of the pool, which are worth
+/// keeping in the lookup table we maintain.
+pub fn static_lookup_table_addresses() -> Vec<Pubkey> {
+    vec![
+        *AMM_V4,
+        *RAYDIUM_AUTHORITY_V4,
+        *OPENBOOK,
+        *TOKEN_PROGRAM,
+        *TOKEN_2022_PROGRAM,
+        *SOL,
+        system_program::id(),
+        compute_budget::id(),
+        spl_associated_token_account::id(),
+    ]
+}
+
+/// Fetches the lookup table at `LOOKUP_TABLE_ADDRESS`, if set, to be loaded
+/// once and shared by every swap
+pub async fn load_configured_lookup_tables(
+    client: &RpcClient,
+    config: &ProgramConfig,
+) -> Result<Vec<AddressLookupTableAccount>> {
+    match &config.lookup_table_address {
+        Some(address) => {
+            get_address_lookup_table_accounts(client, &[Pubkey::from_str(address)?]).await
+        }
+        None => Ok(vec![]),
+    }
+}
+
+/// Fetches and decodes the address lookup tables at the given addresses
+pub async fn get_address_lookup_table_accounts(
+    client: &RpcClient,
+    addresses: &[Pubkey],
+) -> Result<Vec<AddressLookupTableAccount>> {
+    let accounts = client.get_multiple_accounts(addresses).await?;
+
+    addresses
+        .iter()
+        .zip(accounts)
+        .map(|(key, account)| {
+            let account = account.ok_or_else(|| eyre!("lookup table {} not found", key))?;
+            let table = AddressLookupTable::deserialize(&account.data)?;
+            Ok(AddressLookupTableAccount {
+                key: *key,
+                addresses: table.addresses.to_vec(),
+            })
+        })
+        .collect()
+}
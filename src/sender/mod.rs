@@ -0,0 +1,145 @@
+pub mod lookup_table;
+pub mod nonce;
+pub mod paper;
+
+use std::time::Duration;
+
+use eyre::{eyre, Result};
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcSendTransactionConfig};
+use solana_sdk::{
+    address_lookup_table::AddressLookupTableAccount,
+    commitment_config::{CommitmentConfig, CommitmentLevel},
+    hash::Hash,
+    instruction::Instruction,
+    message::{v0, VersionedMessage},
+    signature::Signature,
+    transaction::VersionedTransaction,
+};
+
+use crate::{metrics::rpc_error, signer::TransactionSigner};
+
+/// Time between two signature status polls of a sent transaction
+pub(crate) const CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Polls after which a transaction built against a recent blockhash is
+/// considered dropped, its blockhash has expired by then. Durable nonce
+/// transactions don't expire and are confirmed by
+/// `nonce::confirm_durable_nonce_transaction` instead.
+const CONFIRMATION_POLLS: usize = 180;
+
+/// Compiles the instructions into a v0 message, resolving accounts through
+/// the given lookup tables, and has the payer sign it.
+pub async fn build_versioned_transaction(
+    payer: &dyn TransactionSigner,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+    recent_blockhash: Hash,
+) -> Result<VersionedTransaction> {
+    let message = VersionedMessage::V0(v0::Message::try_compile(
+        &payer.pubkey(),
+        instructions,
+        lookup_tables,
+        recent_blockhash,
+    )?);
+    let signature = payer.sign_message(&message).await?;
+    Ok(VersionedTransaction {
+        signatures: vec![signature],
+        message,
+    })
+}
+
+/// Builds a v0 transaction from the instructions against the latest
+/// finalized blockhash and has the payer sign it.
+pub async fn build_recent_blockhash_transaction(
+    client: &RpcClient,
+    payer: &dyn TransactionSigner,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+) -> Result<VersionedTransaction> {
+    let recent_blockhash = client
+        .get_latest_blockhash_with_commitment(CommitmentConfig {
+            commitment: CommitmentLevel::Finalized,
+        })
+        .await
+        .inspect_err(|_| rpc_error("getLatestBlockhash"))?
+        .0;
+
+    build_versioned_transaction(payer, instructions, lookup_tables, recent_blockhash).await
+}
+
+/// Builds a v0 transaction from the instructions, signs it with the payer and
+/// waits for it to be confirmed.
+pub async fn sign_and_send_instructions(
+    client: &RpcClient,
+    payer: &dyn TransactionSigner,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+) -> Result<Signature> {
+    let transaction =
+        build_recent_blockhash_transaction(client, payer, instructions, lookup_tables).await?;
+
+    send_and_confirm_transaction(client, &transaction).await
+}
+
+/// Sends an already signed transaction and waits for it to be confirmed
+pub async fn send_and_confirm_transaction(
+    client: &RpcClient,
+    transaction: &VersionedTransaction,
+) -> Result<Signature> {
+    let signature = send_transaction(client, transaction).await?;
+    confirm_transaction(client, &signature).await?;
+    Ok(signature)
+}
+
+/// Sends an already signed transaction without waiting for it to land
+pub async fn send_transaction(
+    client: &RpcClient,
+    transaction: &VersionedTransaction,
+) -> Result<Signature> {
+    let signature = client
+        .send_transaction_with_config(
+            transaction,
+            RpcSendTransactionConfig {
+                skip_preflight: true,
+                preflight_commitment: Some(CommitmentLevel::Processed),
+                ..RpcSendTransactionConfig::default()
+            },
+        )
+        .await
+        .inspect_err(|e| {
+            tracing::error!("failed to send transaction: {:?}", e);
+            rpc_error("sendTransaction");
+        })?;
+    Ok(signature)
+}
+
+/// Polls the status of a sent transaction built against a recent blockhash
+/// until it is confirmed, failing when it errored on chain or was dropped
+pub async fn confirm_transaction(client: &RpcClient, signature: &Signature) -> Result<()> {
+    for _ in 0..CONFIRMATION_POLLS {
+        if is_transaction_confirmed(client, signature).await? {
+            return Ok(());
+        }
+        tokio::time::sleep(CONFIRMATION_POLL_INTERVAL).await;
+    }
+    Err(eyre!("transaction {} was not confirmed", signature))
+}
+
+/// Fetches the status of a sent transaction once, returning whether it is
+/// confirmed yet and failing when it errored on chain
+pub(crate) async fn is_transaction_confirmed(
+    client: &RpcClient,
+    signature: &Signature,
+) -> Result<bool> {
+    let status = client
+        .get_signature_status_with_commitment(signature, CommitmentConfig::confirmed())
+        .await
+        .inspect_err(|_| rpc_error("getSignatureStatuses"))?;
+    match status {
+        Some(Ok(())) => Ok(true),
+        Some(Err(e)) => {
+            tracing::error!("transaction {} failed: {:?}", signature, e);
+            Err(e.into())
+        }
+        None => Ok(false),
+    }
+}
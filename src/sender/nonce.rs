@@ -0,0 +1,174 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use eyre::{eyre, Result};
+use lazy_static::lazy_static;
+use solana_client::nonblocking::{
+    nonce_utils::{data_from_account, get_account_with_commitment},
+    rpc_client::RpcClient,
+};
+use solana_sdk::{
+    address_lookup_table::AddressLookupTableAccount,
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    instruction::Instruction,
+    nonce::{state::Data, State},
+    pubkey::Pubkey,
+    signature::Signature,
+    system_instruction, system_program,
+    transaction::VersionedTransaction,
+};
+use tokio::sync::OwnedMutexGuard;
+
+use crate::{
+    sender::{
+        build_versioned_transaction, is_transaction_confirmed, sign_and_send_instructions,
+        CONFIRMATION_POLL_INTERVAL,
+    },
+    signer::TransactionSigner,
+};
+
+/// Time after which a durable nonce transaction that hasn't landed is
+/// invalidated by advancing its nonce
+const NONCE_CONFIRMATION_DEADLINE: Duration = Duration::from_secs(90);
+
+lazy_static! {
+    static ref NONCE_LOCKS: Mutex<HashMap<Pubkey, Arc<tokio::sync::Mutex<()>>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Serializes the transactions built against a nonce account, as the first
+/// one to land advances the nonce and invalidates the others
+pub async fn lock_nonce_account(nonce_account: &Pubkey) -> OwnedMutexGuard<()> {
+    let lock = Arc::clone(
+        NONCE_LOCKS
+            .lock()
+            .unwrap()
+            .entry(*nonce_account)
+            .or_default(),
+    );
+    lock.lock_owned().await
+}
+
+/// Derives the address of the nonce account created from the payer and seed
+pub fn get_nonce_account_address(payer: &Pubkey, seed: &str) -> Result<Pubkey> {
+    Ok(Pubkey::create_with_seed(
+        payer,
+        seed,
+        &system_program::id(),
+    )?)
+}
+
+/// Creates a rent exempt nonce account derived from the payer and the seed,
+/// with the payer as nonce authority.
+pub async fn create_nonce_account(
+    client: &RpcClient,
+    payer: &dyn TransactionSigner,
+    seed: &str,
+) -> Result<Pubkey> {
+    let nonce_account = get_nonce_account_address(&payer.pubkey(), seed)?;
+    let lamports = client
+        .get_minimum_balance_for_rent_exemption(State::size())
+        .await?;
+
+    let instructions = system_instruction::create_nonce_account_with_seed(
+        &payer.pubkey(),
+        &nonce_account,
+        &payer.pubkey(),
+        seed,
+        &payer.pubkey(),
+        lamports,
+    );
+    sign_and_send_instructions(client, payer, &instructions, &[]).await?;
+    Ok(nonce_account)
+}
+
+/// Advances the nonce, which invalidates every transaction pre-signed with the
+/// current nonce value.
+pub async fn advance_nonce_account(
+    client: &RpcClient,
+    payer: &dyn TransactionSigner,
+    nonce_account: &Pubkey,
+) -> Result<Signature> {
+    let instruction = system_instruction::advance_nonce_account(nonce_account, &payer.pubkey());
+    sign_and_send_instructions(client, payer, &[instruction], &[]).await
+}
+
+/// Reads the authority and current durable nonce of a nonce account
+pub async fn get_nonce_data(client: &RpcClient, nonce_account: &Pubkey) -> Result<Data> {
+    let account =
+        get_account_with_commitment(client, nonce_account, CommitmentConfig::confirmed()).await?;
+    Ok(data_from_account(&account)?)
+}
+
+/// Builds a transaction using the current value of the nonce account instead
+/// of a recent blockhash, so it stays valid until the nonce is advanced.
+/// `advance_nonce_account` is prepended as the first instruction, so the
+/// payer must be the authority of `nonce_data`.
+pub async fn build_durable_nonce_transaction(
+    payer: &dyn TransactionSigner,
+    nonce_account: &Pubkey,
+    nonce_data: &Data,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+) -> Result<VersionedTransaction> {
+    let mut nonce_instructions = vec![system_instruction::advance_nonce_account(
+        nonce_account,
+        &payer.pubkey(),
+    )];
+    nonce_instructions.extend_from_slice(instructions);
+
+    build_versioned_transaction(
+        payer,
+        &nonce_instructions,
+        lookup_tables,
+        nonce_data.blockhash(),
+    )
+    .await
+}
+
+/// Polls the status of a sent durable nonce transaction until it is
+/// confirmed. It never expires by blockhash, so it is only considered dropped
+/// once the nonce it was built against has advanced without it landing. Past
+/// `NONCE_CONFIRMATION_DEADLINE` the payer advances the nonce itself, so that
+/// the transaction can't land after being reported as failed.
+pub async fn confirm_durable_nonce_transaction(
+    client: &RpcClient,
+    payer: &dyn TransactionSigner,
+    signature: &Signature,
+    nonce_account: &Pubkey,
+    nonce_blockhash: &Hash,
+) -> Result<()> {
+    let deadline = Instant::now() + NONCE_CONFIRMATION_DEADLINE;
+    loop {
+        if is_transaction_confirmed(client, signature).await? {
+            return Ok(());
+        }
+        let nonce_data = get_nonce_data(client, nonce_account).await?;
+        if nonce_data.blockhash() != *nonce_blockhash {
+            // The transaction itself may have advanced the nonce between the
+            // two reads
+            if is_transaction_confirmed(client, signature).await? {
+                return Ok(());
+            }
+            return Err(eyre!(
+                "nonce account {} advanced without transaction {} landing",
+                nonce_account,
+                signature
+            ));
+        }
+        if Instant::now() >= deadline {
+            tracing::warn!(
+                "transaction {} not confirmed in time, advancing nonce account {}",
+                signature,
+                nonce_account
+            );
+            advance_nonce_account(client, payer, nonce_account).await?;
+            continue;
+        }
+        tokio::time::sleep(CONFIRMATION_POLL_INTERVAL).await;
+    }
+}
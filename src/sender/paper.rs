@@ -0,0 +1,271 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use eyre::{eyre, Result};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+use crate::{
+    constants::{LAMPORTS_PER_SIGNATURE, SOL},
+    types::{MintInfo, PoolInfo},
+    utils::{get_swap_amount_out, get_token_accounts},
+};
+
+/// Simulated fill engine standing in for transaction sending in paper mode.
+/// Swaps are filled against the pool vault reserves read when the swap is
+/// decided, and settled against a virtual book shared by every swapper.
+pub struct PaperEngine {
+    book: Mutex<PaperBook>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct PaperBook {
+    /// Virtual SOL balance, wrapped or not
+    pub lamports: u64,
+    /// Virtual token balances by mint, in base units
+    pub tokens: HashMap<Pubkey, u64>,
+}
+
+impl PaperEngine {
+    pub fn new(lamports: u64) -> Self {
+        Self {
+            book: Mutex::new(PaperBook {
+                lamports,
+                tokens: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Virtual balance of `mint` in base units, lamports for SOL
+    pub fn balance(&self, mint: &Pubkey) -> u64 {
+        let book = self.book.lock().unwrap();
+        if *mint == *SOL {
+            book.lamports
+        } else {
+            book.tokens.get(mint).copied().unwrap_or_default()
+        }
+    }
+
+    pub fn book(&self) -> PaperBook {
+        self.book.lock().unwrap().clone()
+    }
+
+    /// Fills a swap of `amount_in` base units of `mint_in` at the current
+    /// pool reserves. Fails like the swap would on chain when the virtual
+    /// balance is short or the fill is below `minimum_amount_out`.
+    pub async fn fill(
+        &self,
+        client: &RpcClient,
+        pool_info: &PoolInfo,
+        (mint_in, mint_out): (&MintInfo, &MintInfo),
+        epoch: u64,
+        amount_in: u64,
+        minimum_amount_out: u64,
+    ) -> Result<(u64, Signature)> {
+        let vaults =
+            get_token_accounts(client, &[pool_info.base_vault, pool_info.quote_vault]).await?;
+        // safe to unwrap, because `[get_token_accounts]` checks that returned
+        // vector length matches the input vector length
+        self.fill_at_reserves(
+            pool_info,
+            (mint_in, mint_out),
+            epoch,
+            (
+                vaults.first().unwrap().amount,
+                vaults.get(1).unwrap().amount,
+            ),
+            amount_in,
+            minimum_amount_out,
+        )
+    }
+
+    /// Fills a swap against the given base and quote vault amounts, as
+    /// recorded during a live run
+    pub fn fill_at_reserves(
+        &self,
+        pool_info: &PoolInfo,
+        (mint_in, mint_out): (&MintInfo, &MintInfo),
+        epoch: u64,
+        (base_vault_amount, quote_vault_amount): (u64, u64),
+        amount_in: u64,
+        minimum_amount_out: u64,
+    ) -> Result<(u64, Signature)> {
+        // Token-2022 transfer fees are withheld on the way in and out
+        let pool_amount_in = amount_in - mint_in.transfer_fee(amount_in, epoch);
+        let pool_amount_out = get_swap_amount_out(
+            pool_info,
+            base_vault_amount,
+            quote_vault_amount,
+            pool_amount_in,
+            pool_info.base_mint == mint_in.mint,
+        );
+        let amount_out = pool_amount_out - mint_out.transfer_fee(pool_amount_out, epoch);
+        if amount_out < minimum_amount_out {
+            return Err(eyre!(
+                "paper fill of {} below minimum out {}",
+                amount_out,
+                minimum_amount_out
+            ));
+        }
+
+        let mut book = self.book.lock().unwrap();
+        let lamports_debited = if mint_in.mint == *SOL {
+            amount_in + LAMPORTS_PER_SIGNATURE
+        } else {
+            LAMPORTS_PER_SIGNATURE
+        };
+        if book.lamports < lamports_debited {
+            return Err(eyre!("paper balance too low: {} lamports", book.lamports));
+        }
+        if mint_in.mint != *SOL {
+            let balance = book.tokens.entry(mint_in.mint).or_default();
+            if *balance < amount_in {
+                return Err(eyre!("paper balance too low: {} {}", balance, mint_in.mint));
+            }
+            *balance -= amount_in;
+        }
+        book.lamports -= lamports_debited;
+        if mint_out.mint == *SOL {
+            book.lamports += amount_out;
+        } else {
+            *book.tokens.entry(mint_out.mint).or_default() += amount_out;
+        }
+
+        let signature = Signature::new_unique();
+        tracing::info!(
+            "paper fill {} {} for {} {}, balance {} lamports (sig: {:?})",
+            amount_in,
+            mint_in.mint,
+            amount_out,
+            mint_out.mint,
+            book.lamports,
+            signature
+        );
+        Ok((amount_out, signature))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::TOKEN_PROGRAM;
+
+    const TOKEN_RESERVE: u64 = 1_000_000_000;
+    const SOL_RESERVE: u64 = 100_000_000_000;
+    const PAPER_LAMPORTS: u64 = 10_000_000_000;
+
+    fn mint_info(mint: Pubkey) -> MintInfo {
+        MintInfo {
+            mint,
+            token_program: *TOKEN_PROGRAM,
+            transfer_fee_config: None,
+        }
+    }
+
+    fn pool_info(token: &MintInfo) -> PoolInfo {
+        PoolInfo {
+            base_mint: token.mint,
+            quote_mint: *SOL,
+            swap_fee_numerator: 25,
+            swap_fee_denominator: 10_000,
+            ..PoolInfo::default()
+        }
+    }
+
+    #[test]
+    fn buy_fills_at_the_pool_quote_and_debits_the_signature_fee() {
+        let engine = PaperEngine::new(PAPER_LAMPORTS);
+        let (sol, token) = (mint_info(*SOL), mint_info(Pubkey::new_unique()));
+
+        let (amount_out, _) = engine
+            .fill_at_reserves(
+                &pool_info(&token),
+                (&sol, &token),
+                0,
+                (TOKEN_RESERVE, SOL_RESERVE),
+                1_000_000_000,
+                0,
+            )
+            .unwrap();
+
+        // 0.25% swap fee, then 1e9 * 997.5e6 / (100e9 + 997.5e6)
+        assert_eq!(amount_out, 9_876_482);
+        assert_eq!(engine.balance(&token.mint), amount_out);
+        assert_eq!(
+            engine.balance(&SOL),
+            PAPER_LAMPORTS - 1_000_000_000 - LAMPORTS_PER_SIGNATURE
+        );
+    }
+
+    #[test]
+    fn sell_credits_the_sol_out() {
+        let engine = PaperEngine::new(PAPER_LAMPORTS);
+        let (sol, token) = (mint_info(*SOL), mint_info(Pubkey::new_unique()));
+        let pool_info = pool_info(&token);
+        let (tokens, _) = engine
+            .fill_at_reserves(
+                &pool_info,
+                (&sol, &token),
+                0,
+                (TOKEN_RESERVE, SOL_RESERVE),
+                1_000_000_000,
+                0,
+            )
+            .unwrap();
+
+        let (lamports_out, _) = engine
+            .fill_at_reserves(
+                &pool_info,
+                (&token, &sol),
+                0,
+                (TOKEN_RESERVE - tokens, SOL_RESERVE + 1_000_000_000),
+                tokens,
+                0,
+            )
+            .unwrap();
+
+        // Both swap fees are lost on the round trip
+        assert_eq!(lamports_out, 995_055_359);
+        assert_eq!(engine.balance(&token.mint), 0);
+        assert_eq!(
+            engine.balance(&SOL),
+            PAPER_LAMPORTS - 1_000_000_000 + lamports_out - 2 * LAMPORTS_PER_SIGNATURE
+        );
+    }
+
+    #[test]
+    fn fill_below_minimum_out_leaves_the_book_unchanged() {
+        let engine = PaperEngine::new(PAPER_LAMPORTS);
+        let (sol, token) = (mint_info(*SOL), mint_info(Pubkey::new_unique()));
+
+        let result = engine.fill_at_reserves(
+            &pool_info(&token),
+            (&sol, &token),
+            0,
+            (TOKEN_RESERVE, SOL_RESERVE),
+            1_000_000_000,
+            9_876_483,
+        );
+
+        assert!(result.is_err());
+        assert_eq!(engine.balance(&SOL), PAPER_LAMPORTS);
+        assert_eq!(engine.balance(&token.mint), 0);
+    }
+
+    #[test]
+    fn sell_without_token_balance_fails() {
+        let engine = PaperEngine::new(PAPER_LAMPORTS);
+        let (sol, token) = (mint_info(*SOL), mint_info(Pubkey::new_unique()));
+
+        let result = engine.fill_at_reserves(
+            &pool_info(&token),
+            (&token, &sol),
+            0,
+            (TOKEN_RESERVE, SOL_RESERVE),
+            1_000,
+            0,
+        );
+
+        assert!(result.is_err());
+        assert_eq!(engine.balance(&SOL), PAPER_LAMPORTS);
+    }
+}
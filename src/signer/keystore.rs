@@ -0,0 +1,106 @@
+use std::path::Path;
+
+use aes_gcm::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Key,
+};
+use serde::{Deserialize, Serialize};
+use solana_sdk::signature::Keypair;
+
+use crate::signer::{keypair_from_bytes, SignerError};
+
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+
+/// Keypair encrypted with AES-256-GCM, under a key derived from the
+/// passphrase with scrypt.
+#[derive(Debug, Serialize, Deserialize)]
+struct Keystore {
+    scrypt_log_n: u8,
+    scrypt_r: u32,
+    scrypt_p: u32,
+    salt: Vec<u8>,
+    nonce: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+/// Reads the passphrase from `KEYSTORE_PASSPHRASE`, or prompts for it
+pub fn read_passphrase(prompt: &str) -> Result<String, SignerError> {
+    if let Ok(passphrase) = std::env::var("KEYSTORE_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+    rpassword::prompt_password(prompt).map_err(SignerError::Passphrase)
+}
+
+pub fn encrypt_keystore_file(
+    path: &Path,
+    keypair: &Keypair,
+    passphrase: &str,
+) -> Result<(), SignerError> {
+    let params = scrypt::Params::recommended();
+    let mut salt = vec![0; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+
+    let cipher = Aes256Gcm::new(&derive_key(passphrase, &salt, &params)?);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, keypair.to_bytes().as_slice())
+        .map_err(|_| SignerError::Encryption)?;
+
+    let keystore = Keystore {
+        scrypt_log_n: params.log_n(),
+        scrypt_r: params.r(),
+        scrypt_p: params.p(),
+        salt,
+        nonce: nonce.to_vec(),
+        ciphertext,
+    };
+    // Serializing plain byte vectors cannot fail
+    let content = serde_json::to_string(&keystore).unwrap();
+    std::fs::write(path, content).map_err(|source| SignerError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+pub fn decrypt_keystore_file(path: &Path, passphrase: &str) -> Result<Keypair, SignerError> {
+    let content = std::fs::read_to_string(path).map_err(|source| SignerError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let keystore: Keystore =
+        serde_json::from_str(&content).map_err(|source| SignerError::Json {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+    let params = scrypt::Params::new(
+        keystore.scrypt_log_n,
+        keystore.scrypt_r,
+        keystore.scrypt_p,
+        KEY_LEN,
+    )
+    .map_err(|_| SignerError::KeyDerivation)?;
+    let nonce: [u8; 12] = keystore
+        .nonce
+        .as_slice()
+        .try_into()
+        .map_err(|_| SignerError::Decryption)?;
+
+    let cipher = Aes256Gcm::new(&derive_key(passphrase, &keystore.salt, &params)?);
+    let bytes = cipher
+        .decrypt(&nonce.into(), keystore.ciphertext.as_slice())
+        .map_err(|_| SignerError::Decryption)?;
+    keypair_from_bytes(&bytes)
+}
+
+fn derive_key(
+    passphrase: &str,
+    salt: &[u8],
+    params: &scrypt::Params,
+) -> Result<Key<Aes256Gcm>, SignerError> {
+    let mut key = [0; KEY_LEN];
+    scrypt::scrypt(passphrase.as_bytes(), salt, params, &mut key)
+        .map_err(|_| SignerError::KeyDerivation)?;
+    Ok(key.into())
+}
@@ -0,0 +1,264 @@
+pub mod keystore;
+pub mod policy;
+pub mod remote;
+
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use solana_sdk::{
+    bs58,
+    message::VersionedMessage,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::Signer,
+};
+
+use crate::{
+    constants::{COMPUTE_UNIT_LIMIT, COMPUTE_UNIT_PRICE_MICRO_LAMPORTS, LAMPORTS_PER_SOL},
+    signer::{
+        policy::{LocalSigningService, SigningPolicy},
+        remote::RemoteSigner,
+    },
+    types::ProgramConfig,
+};
+
+/// Signs transaction messages on behalf of the fee payer, either in process
+/// or by calling out to a signing service.
+#[async_trait]
+pub trait TransactionSigner: Send + Sync {
+    fn pubkey(&self) -> Pubkey;
+
+    async fn sign_message(&self, message: &VersionedMessage) -> Result<Signature, SignerError>;
+}
+
+#[async_trait]
+impl TransactionSigner for Keypair {
+    fn pubkey(&self) -> Pubkey {
+        Signer::pubkey(self)
+    }
+
+    async fn sign_message(&self, message: &VersionedMessage) -> Result<Signature, SignerError> {
+        Ok(Signer::sign_message(self, &message.serialize()))
+    }
+}
+
+const SIGNER_VARIABLES: &str =
+    "BUYER_KEYPAIR_PATH, BUYER_KEYSTORE_PATH, BUYER_PRIVATE_KEY or REMOTE_SIGNER_URL";
+
+#[derive(Debug, thiserror::Error)]
+pub enum SignerError {
+    #[error("no signer configured, set one of {}", SIGNER_VARIABLES)]
+    NotConfigured,
+    #[error("only one of {} can be set", SIGNER_VARIABLES)]
+    MultipleConfigured,
+    #[error("REMOTE_SIGNER_PUBKEY must be set to a valid public key with REMOTE_SIGNER_URL")]
+    MissingRemotePubkey,
+    #[error("unsupported remote signer url {0}, expected http(s)://... or unix:<path>")]
+    InvalidRemoteUrl(String),
+    #[error("the keypair is held by a remote signer")]
+    NotLocal,
+    #[error("failed to read {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("failed to parse {path}: {source}")]
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[error("private key is not valid base58")]
+    InvalidBase58,
+    #[error("invalid keypair bytes: {0}")]
+    InvalidKeypair(String),
+    #[error("failed to read passphrase: {0}")]
+    Passphrase(std::io::Error),
+    #[error("invalid key derivation parameters")]
+    KeyDerivation,
+    #[error("failed to encrypt keypair")]
+    Encryption,
+    #[error("wrong passphrase or corrupted keystore")]
+    Decryption,
+    #[error("remote signer request failed: {0}")]
+    Remote(String),
+    #[error("remote signer did not answer within {0:?}")]
+    Timeout(Duration),
+    #[error("remote signer returned an invalid signature")]
+    InvalidSignature,
+    #[error("{0} exists and is not a socket")]
+    NotASocket(PathBuf),
+    #[error("signing policy violation: {0}")]
+    PolicyViolation(String),
+}
+
+/// What a loaded signer signs, only trading goes through the signing policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignerUse {
+    /// Swaps, checked against `SIGNING_POLICY_MAX_SOL` when set
+    Trading,
+    /// Wallet, lookup table, nonce and rent management, which the policy
+    /// would reject
+    Admin,
+}
+
+/// Where the buyer keypair is loaded from
+#[derive(Debug, Clone)]
+pub enum SignerSource {
+    /// JSON byte array file, as written by `solana-keygen new`
+    KeypairFile(PathBuf),
+    /// Base58 encoded private key, as exported by most wallets
+    Base58(String),
+    /// Passphrase encrypted keypair, see `keystore` subcommand
+    Keystore(PathBuf),
+    /// Signing service holding the key outside of the bot process
+    Remote { url: String, pubkey: Pubkey },
+}
+
+impl SignerSource {
+    pub fn from_config(config: &ProgramConfig) -> Result<Self, SignerError> {
+        let mut sources = vec![];
+        if let Some(path) = &config.buyer_keypair_path {
+            sources.push(SignerSource::KeypairFile(path.into()));
+        }
+        if let Some(path) = &config.buyer_keystore_path {
+            sources.push(SignerSource::Keystore(path.into()));
+        }
+        if let Some(private_key) = &config.buyer_private_key {
+            sources.push(SignerSource::Base58(private_key.clone()));
+        }
+        if let Some(url) = &config.remote_signer_url {
+            let pubkey = config
+                .remote_signer_pubkey
+                .as_deref()
+                .and_then(|pubkey| Pubkey::from_str(pubkey).ok())
+                .ok_or(SignerError::MissingRemotePubkey)?;
+            sources.push(SignerSource::Remote {
+                url: url.clone(),
+                pubkey,
+            });
+        }
+
+        match sources.len() {
+            0 => Err(SignerError::NotConfigured),
+            1 => Ok(sources.remove(0)),
+            _ => Err(SignerError::MultipleConfigured),
+        }
+    }
+
+    /// Loads the transaction signer. Local keypairs used for trading are
+    /// wrapped in a [`LocalSigningService`] when `SIGNING_POLICY_MAX_SOL` is
+    /// set, so they go through the same checks as a remote signing service.
+    pub fn load(
+        &self,
+        config: &ProgramConfig,
+        usage: SignerUse,
+    ) -> Result<Arc<dyn TransactionSigner>, SignerError> {
+        if let SignerSource::Remote { url, pubkey } = self {
+            return Ok(Arc::new(RemoteSigner::new(url, *pubkey)?));
+        }
+
+        Ok(with_signing_policy(self.load_keypair()?, config, usage))
+    }
+
+    /// Loads the keypair, prompting for the passphrase when unlocking a
+    /// keystore unless `KEYSTORE_PASSPHRASE` is set.
+    pub fn load_keypair(&self) -> Result<Keypair, SignerError> {
+        match self {
+            SignerSource::KeypairFile(path) => read_keypair_file(path),
+            SignerSource::Base58(private_key) => {
+                let bytes = bs58::decode(private_key)
+                    .into_vec()
+                    .map_err(|_| SignerError::InvalidBase58)?;
+                keypair_from_bytes(&bytes)
+            }
+            SignerSource::Keystore(path) => {
+                let passphrase = keystore::read_passphrase("Keystore passphrase: ")?;
+                keystore::decrypt_keystore_file(path, &passphrase)
+            }
+            SignerSource::Remote { .. } => Err(SignerError::NotLocal),
+        }
+    }
+}
+
+/// Loads the trading wallets from the JSON keypair files in `WALLETS_DIR`,
+/// or only `signer` when it isn't set.
+pub fn load_wallets(
+    config: &ProgramConfig,
+    signer: Arc<dyn TransactionSigner>,
+    usage: SignerUse,
+) -> Result<Vec<Arc<dyn TransactionSigner>>, SignerError> {
+    let Some(dir) = &config.wallets_dir else {
+        return Ok(vec![signer]);
+    };
+
+    let entries = std::fs::read_dir(dir).map_err(|source| SignerError::Io {
+        path: dir.into(),
+        source,
+    })?;
+    let mut paths = vec![];
+    for entry in entries {
+        let path = entry
+            .map_err(|source| SignerError::Io {
+                path: dir.into(),
+                source,
+            })?
+            .path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| Ok(with_signing_policy(read_keypair_file(path)?, config, usage)))
+        .collect()
+}
+
+/// Wraps a local keypair used for trading in a [`LocalSigningService`] when
+/// `SIGNING_POLICY_MAX_SOL` is set
+fn with_signing_policy(
+    keypair: Keypair,
+    config: &ProgramConfig,
+    usage: SignerUse,
+) -> Arc<dyn TransactionSigner> {
+    match config.signing_policy_max_sol {
+        Some(max_sol) if usage == SignerUse::Trading => Arc::new(LocalSigningService::new(
+            keypair,
+            SigningPolicy {
+                max_lamports_in: (max_sol * *LAMPORTS_PER_SOL) as u64,
+                max_cu_price: config
+                    .signing_policy_max_cu_price
+                    .unwrap_or(COMPUTE_UNIT_PRICE_MICRO_LAMPORTS),
+                max_cu_limit: config
+                    .signing_policy_max_cu_limit
+                    .unwrap_or(COMPUTE_UNIT_LIMIT),
+            },
+        )),
+        _ => Arc::new(keypair),
+    }
+}
+
+fn read_keypair_file(path: &Path) -> Result<Keypair, SignerError> {
+    let content = std::fs::read_to_string(path).map_err(|source| SignerError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let bytes: Vec<u8> = serde_json::from_str(&content).map_err(|source| SignerError::Json {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    keypair_from_bytes(&bytes)
+}
+
+pub(crate) fn keypair_from_bytes(bytes: &[u8]) -> Result<Keypair, SignerError> {
+    Keypair::from_bytes(bytes).map_err(|e| SignerError::InvalidKeypair(e.to_string()))
+}
@@ -0,0 +1,574 @@
+use async_trait::async_trait;
+use solana_sdk::{
+    compute_budget::{self, ComputeBudgetInstruction},
+    instruction::CompiledInstruction,
+    message::VersionedMessage,
+    program_utils::limited_deserialize,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    system_instruction::SystemInstruction,
+    system_program,
+};
+use spl_associated_token_account::{
+    get_associated_token_address, get_associated_token_address_with_program_id,
+};
+
+use crate::{
+    constants::{AMM_V4, SOL, TOKEN_2022_PROGRAM, TOKEN_PROGRAM},
+    signer::{SignerError, TransactionSigner},
+};
+
+/// Raydium AMM v4 `SwapBaseIn` instruction tag
+const SWAP_BASE_IN_TAG: u8 = 9;
+/// Positions of the user accounts in `SwapBaseIn`
+const SWAP_SOURCE_ACCOUNT_INDEX: usize = 15;
+const SWAP_DESTINATION_ACCOUNT_INDEX: usize = 16;
+const SWAP_OWNER_ACCOUNT_INDEX: usize = 17;
+/// SPL Token `CloseAccount` and `SyncNative` instruction tags
+const CLOSE_ACCOUNT_TAG: u8 = 9;
+const SYNC_NATIVE_TAG: u8 = 17;
+/// Position of the lamports destination in `CloseAccount`
+const CLOSE_ACCOUNT_DESTINATION_INDEX: usize = 1;
+/// Position of the transfer destination in `SystemInstruction::Transfer`
+const TRANSFER_DESTINATION_INDEX: usize = 1;
+/// Positions of the accounts in the associated token account `Create`
+/// and `CreateIdempotent` instructions
+const CREATE_ATA_PAYER_INDEX: usize = 0;
+const CREATE_ATA_ACCOUNT_INDEX: usize = 1;
+const CREATE_ATA_WALLET_INDEX: usize = 2;
+const CREATE_ATA_MINT_INDEX: usize = 3;
+/// Compute budget `SetComputeUnitLimit` and `SetComputeUnitPrice` instruction
+/// tags
+const SET_COMPUTE_UNIT_LIMIT_TAG: u8 = 2;
+const SET_COMPUTE_UNIT_PRICE_TAG: u8 = 3;
+/// Compute units a transaction can use at most, assumed when the message
+/// doesn't set a limit
+const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+const MICRO_LAMPORTS_PER_LAMPORT: u128 = 1_000_000;
+
+/// Per-transaction checks a signing service runs before signing: only
+/// Raydium swaps between the signer's own token accounts and the
+/// instructions the swapper bundles with them are allowed, and no more than
+/// `max_lamports_in` SOL can be spent on swaps and priority fees.
+#[derive(Debug, Clone)]
+pub struct SigningPolicy {
+    pub max_lamports_in: u64,
+    /// Highest compute unit price, in micro-lamports
+    pub max_cu_price: u64,
+    pub max_cu_limit: u32,
+}
+
+impl SigningPolicy {
+    pub fn check(&self, signer: &Pubkey, message: &VersionedMessage) -> Result<(), SignerError> {
+        let account_keys = message.static_account_keys();
+        let wsol_token_account = get_associated_token_address(signer, &SOL);
+
+        // Token accounts the signer is proven to own: the WSOL account, and
+        // the accounts the message creates for the signer. Swaps can only
+        // move tokens between them.
+        let mut signer_token_accounts = vec![wsol_token_account];
+        for instruction in message.instructions() {
+            if *instruction.program_id(account_keys) != spl_associated_token_account::id() {
+                continue;
+            }
+            let account = |index| static_account(account_keys, instruction, index);
+            if account(CREATE_ATA_PAYER_INDEX)? != signer
+                || account(CREATE_ATA_WALLET_INDEX)? != signer
+            {
+                return Err(violation("token account created for another wallet"));
+            }
+            let token_account = account(CREATE_ATA_ACCOUNT_INDEX)?;
+            // The WSOL mint is loaded from the lookup table, its account is known
+            if *token_account != wsol_token_account {
+                let mint = account(CREATE_ATA_MINT_INDEX)?;
+                let is_associated =
+                    [*TOKEN_PROGRAM, *TOKEN_2022_PROGRAM]
+                        .iter()
+                        .any(|token_program| {
+                            get_associated_token_address_with_program_id(
+                                signer,
+                                mint,
+                                token_program,
+                            ) == *token_account
+                        });
+                if !is_associated {
+                    return Err(violation(
+                        "token account is not an associated token account",
+                    ));
+                }
+            }
+            signer_token_accounts.push(*token_account);
+        }
+
+        let mut lamports_in: u64 = 0;
+        let mut cu_price = 0;
+        let mut cu_limit = MAX_COMPUTE_UNIT_LIMIT;
+        for instruction in message.instructions() {
+            let program_id = instruction.program_id(account_keys);
+            let account = |index| static_account(account_keys, instruction, index);
+
+            if *program_id == spl_associated_token_account::id() {
+                continue;
+            }
+
+            if *program_id == compute_budget::id() {
+                match parse_compute_budget_instruction(instruction) {
+                    Some(ComputeBudgetInstruction::SetComputeUnitPrice(price)) => {
+                        if price > self.max_cu_price {
+                            return Err(violation(&format!(
+                                "compute unit price {} above the {} micro-lamports cap",
+                                price, self.max_cu_price
+                            )));
+                        }
+                        cu_price = price;
+                    }
+                    Some(ComputeBudgetInstruction::SetComputeUnitLimit(limit)) => {
+                        if limit > self.max_cu_limit {
+                            return Err(violation(&format!(
+                                "compute unit limit {} above the {} cap",
+                                limit, self.max_cu_limit
+                            )));
+                        }
+                        cu_limit = limit;
+                    }
+                    _ => {
+                        return Err(violation(
+                            "compute budget instruction other than unit price or limit",
+                        ))
+                    }
+                }
+                continue;
+            }
+
+            if *program_id == system_program::id() {
+                match limited_deserialize(&instruction.data) {
+                    // Wrapping moves SOL between the signer's own accounts,
+                    // the swap out of the WSOL account is what spends it
+                    Ok(SystemInstruction::Transfer { .. })
+                        if account(TRANSFER_DESTINATION_INDEX)? == &wsol_token_account => {}
+                    Ok(SystemInstruction::AdvanceNonceAccount) => {}
+                    _ => return Err(violation("system instruction other than wrapping SOL")),
+                }
+                continue;
+            }
+
+            if *program_id == *TOKEN_PROGRAM || *program_id == *TOKEN_2022_PROGRAM {
+                match instruction.data.first() {
+                    Some(&SYNC_NATIVE_TAG) => {}
+                    Some(&CLOSE_ACCOUNT_TAG)
+                        if account(CLOSE_ACCOUNT_DESTINATION_INDEX)? == signer => {}
+                    _ => return Err(violation("token instruction other than wrap/unwrap")),
+                }
+                continue;
+            }
+
+            if *program_id == *AMM_V4 {
+                let amount_in = parse_swap_base_in_amount(instruction)
+                    .ok_or_else(|| violation("Raydium instruction other than swap base in"))?;
+                if account(SWAP_OWNER_ACCOUNT_INDEX)? != signer {
+                    return Err(violation("swap owner is not the signer"));
+                }
+                let source = account(SWAP_SOURCE_ACCOUNT_INDEX)?;
+                let destination = account(SWAP_DESTINATION_ACCOUNT_INDEX)?;
+                if !signer_token_accounts.contains(source)
+                    || !signer_token_accounts.contains(destination)
+                {
+                    return Err(violation("swap between accounts not owned by the signer"));
+                }
+                if *source == wsol_token_account {
+                    lamports_in = lamports_in.saturating_add(amount_in);
+                }
+                continue;
+            }
+
+            return Err(violation(&format!("program {} is not allowed", program_id)));
+        }
+
+        // The priority fee is paid on top of the swapped SOL
+        let priority_fee: u64 = (cu_price as u128 * cu_limit as u128)
+            .div_ceil(MICRO_LAMPORTS_PER_LAMPORT)
+            .try_into()
+            .unwrap_or(u64::MAX);
+        let lamports_in = lamports_in.saturating_add(priority_fee);
+        if lamports_in > self.max_lamports_in {
+            return Err(violation(&format!(
+                "spends {} lamports, above the {} lamports cap",
+                lamports_in, self.max_lamports_in
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Account at `index` of the instruction. Accounts loaded from lookup tables
+/// can't be checked here, so only static keys are accepted where the policy
+/// inspects them.
+fn static_account<'a>(
+    account_keys: &'a [Pubkey],
+    instruction: &CompiledInstruction,
+    index: usize,
+) -> Result<&'a Pubkey, SignerError> {
+    let key_index = *instruction
+        .accounts
+        .get(index)
+        .ok_or_else(|| violation("instruction is missing accounts"))?;
+    account_keys
+        .get(key_index as usize)
+        .ok_or_else(|| violation("inspected account is loaded from a lookup table"))
+}
+
+fn parse_swap_base_in_amount(instruction: &CompiledInstruction) -> Option<u64> {
+    match instruction.data.split_first() {
+        Some((&SWAP_BASE_IN_TAG, rest)) if rest.len() == 16 => {
+            Some(u64::from_le_bytes(rest[..8].try_into().ok()?))
+        }
+        _ => None,
+    }
+}
+
+fn parse_compute_budget_instruction(
+    instruction: &CompiledInstruction,
+) -> Option<ComputeBudgetInstruction> {
+    match instruction.data.split_first() {
+        Some((&SET_COMPUTE_UNIT_LIMIT_TAG, rest)) => {
+            Some(ComputeBudgetInstruction::SetComputeUnitLimit(
+                u32::from_le_bytes(rest.try_into().ok()?),
+            ))
+        }
+        Some((&SET_COMPUTE_UNIT_PRICE_TAG, rest)) => {
+            Some(ComputeBudgetInstruction::SetComputeUnitPrice(
+                u64::from_le_bytes(rest.try_into().ok()?),
+            ))
+        }
+        _ => None,
+    }
+}
+
+fn violation(reason: &str) -> SignerError {
+    SignerError::PolicyViolation(reason.to_string())
+}
+
+/// In-process stand-in for the remote signing service: checks every message
+/// against the policy before signing it with a local keypair.
+pub struct LocalSigningService {
+    keypair: Keypair,
+    policy: SigningPolicy,
+}
+
+impl LocalSigningService {
+    pub fn new(keypair: Keypair, policy: SigningPolicy) -> Self {
+        Self { keypair, policy }
+    }
+}
+
+#[async_trait]
+impl TransactionSigner for LocalSigningService {
+    fn pubkey(&self) -> Pubkey {
+        TransactionSigner::pubkey(&self.keypair)
+    }
+
+    async fn sign_message(&self, message: &VersionedMessage) -> Result<Signature, SignerError> {
+        self.policy.check(&self.pubkey(), message)?;
+        self.keypair.sign_message(message).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{
+        address_lookup_table::AddressLookupTableAccount,
+        hash::Hash,
+        instruction::{AccountMeta, Instruction},
+        message::v0,
+        signature::Signer,
+        system_instruction,
+    };
+    use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+
+    use super::*;
+    use crate::{
+        constants::{OPENBOOK, RAYDIUM_AUTHORITY_V4},
+        sender::lookup_table::static_lookup_table_addresses,
+    };
+
+    const MAX_LAMPORTS_IN: u64 = 500_000_000;
+    const MAX_CU_PRICE: u64 = 1_000_000;
+    const MAX_CU_LIMIT: u32 = 200_000;
+
+    struct Wallet {
+        keypair: Keypair,
+        mint: Pubkey,
+    }
+
+    impl Wallet {
+        fn new() -> Self {
+            Self {
+                keypair: Keypair::new(),
+                mint: Pubkey::new_unique(),
+            }
+        }
+
+        fn pubkey(&self) -> Pubkey {
+            Signer::pubkey(&self.keypair)
+        }
+
+        fn wsol_token_account(&self) -> Pubkey {
+            get_associated_token_address(&self.pubkey(), &SOL)
+        }
+
+        fn token_account(&self) -> Pubkey {
+            get_associated_token_address(&self.pubkey(), &self.mint)
+        }
+
+        fn create_token_account(&self, mint: &Pubkey) -> Instruction {
+            create_associated_token_account_idempotent(
+                &self.pubkey(),
+                &self.pubkey(),
+                mint,
+                &TOKEN_PROGRAM,
+            )
+        }
+
+        fn buy(&self, lamports: u64) -> Vec<Instruction> {
+            vec![
+                self.create_token_account(&SOL),
+                system_instruction::transfer(&self.pubkey(), &self.wsol_token_account(), lamports),
+                spl_token::instruction::sync_native(&TOKEN_PROGRAM, &self.wsol_token_account())
+                    .unwrap(),
+                self.create_token_account(&self.mint),
+                swap_base_in(
+                    lamports,
+                    self.wsol_token_account(),
+                    self.token_account(),
+                    self.pubkey(),
+                ),
+            ]
+        }
+
+        fn sell(&self, tokens: u64, destination: Pubkey) -> Vec<Instruction> {
+            vec![
+                self.create_token_account(&self.mint),
+                swap_base_in(tokens, self.token_account(), destination, self.pubkey()),
+                spl_token::instruction::close_account(
+                    &TOKEN_PROGRAM,
+                    &self.wsol_token_account(),
+                    &self.pubkey(),
+                    &self.pubkey(),
+                    &[],
+                )
+                .unwrap(),
+            ]
+        }
+
+        fn check(
+            &self,
+            instructions: &[Instruction],
+            lookup_table_addresses: Vec<Pubkey>,
+        ) -> Result<(), SignerError> {
+            self.check_with_cap(MAX_LAMPORTS_IN, instructions, lookup_table_addresses)
+        }
+
+        fn check_with_cap(
+            &self,
+            max_lamports_in: u64,
+            instructions: &[Instruction],
+            lookup_table_addresses: Vec<Pubkey>,
+        ) -> Result<(), SignerError> {
+            let lookup_table = AddressLookupTableAccount {
+                key: Pubkey::new_unique(),
+                addresses: lookup_table_addresses,
+            };
+            let message = v0::Message::try_compile(
+                &self.pubkey(),
+                instructions,
+                &[lookup_table],
+                Hash::default(),
+            )
+            .unwrap();
+            SigningPolicy {
+                max_lamports_in,
+                max_cu_price: MAX_CU_PRICE,
+                max_cu_limit: MAX_CU_LIMIT,
+            }
+            .check(&self.pubkey(), &VersionedMessage::V0(message))
+        }
+    }
+
+    fn with_compute_budget(
+        price: u64,
+        limit: u32,
+        instructions: Vec<Instruction>,
+    ) -> Vec<Instruction> {
+        let mut budgeted = vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(limit),
+            ComputeBudgetInstruction::set_compute_unit_price(price),
+        ];
+        budgeted.extend(instructions);
+        budgeted
+    }
+
+    fn swap_base_in(
+        amount_in: u64,
+        source: Pubkey,
+        destination: Pubkey,
+        owner: Pubkey,
+    ) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new_readonly(*TOKEN_PROGRAM, false),
+            AccountMeta::new(Pubkey::new_unique(), false),
+            AccountMeta::new_readonly(*RAYDIUM_AUTHORITY_V4, false),
+        ];
+        accounts.extend((0..4).map(|_| AccountMeta::new(Pubkey::new_unique(), false)));
+        accounts.push(AccountMeta::new_readonly(*OPENBOOK, false));
+        accounts.extend((0..6).map(|_| AccountMeta::new(Pubkey::new_unique(), false)));
+        accounts.push(AccountMeta::new_readonly(Pubkey::new_unique(), false));
+        accounts.push(AccountMeta::new(source, false));
+        accounts.push(AccountMeta::new(destination, false));
+        accounts.push(AccountMeta::new_readonly(owner, true));
+
+        let mut data = vec![SWAP_BASE_IN_TAG];
+        data.extend_from_slice(&amount_in.to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+        Instruction {
+            program_id: *AMM_V4,
+            accounts,
+            data,
+        }
+    }
+
+    fn assert_violation(result: Result<(), SignerError>, reason: &str) {
+        match result {
+            Err(SignerError::PolicyViolation(violation)) => assert!(
+                violation.contains(reason),
+                "expected {:?}, got {:?}",
+                reason,
+                violation
+            ),
+            result => panic!("expected a violation, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn allows_buy_counting_the_swapped_sol_once() {
+        let wallet = Wallet::new();
+        let result = wallet.check(&wallet.buy(300_000_000), static_lookup_table_addresses());
+        assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[test]
+    fn allows_sell_to_the_signer_wsol_account() {
+        let wallet = Wallet::new();
+        let instructions = wallet.sell(1_000_000_000_000, wallet.wsol_token_account());
+        let result = wallet.check(&instructions, static_lookup_table_addresses());
+        assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[test]
+    fn rejects_buy_over_the_cap() {
+        let wallet = Wallet::new();
+        let result = wallet.check(&wallet.buy(600_000_000), static_lookup_table_addresses());
+        assert_violation(result, "above the 500000000 lamports cap");
+    }
+
+    #[test]
+    fn rejects_foreign_program() {
+        let wallet = Wallet::new();
+        let mut instructions = wallet.buy(100_000_000);
+        instructions.push(Instruction {
+            program_id: Pubkey::new_unique(),
+            accounts: vec![AccountMeta::new(wallet.pubkey(), true)],
+            data: vec![],
+        });
+        let result = wallet.check(&instructions, static_lookup_table_addresses());
+        assert_violation(result, "is not allowed");
+    }
+
+    #[test]
+    fn rejects_account_loaded_from_lookup_table() {
+        let wallet = Wallet::new();
+        let mut addresses = static_lookup_table_addresses();
+        addresses.push(wallet.wsol_token_account());
+        let result = wallet.check(&wallet.buy(100_000_000), addresses);
+        assert_violation(result, "loaded from a lookup table");
+    }
+
+    #[test]
+    fn rejects_sell_to_another_account() {
+        let wallet = Wallet::new();
+        let instructions = wallet.sell(1_000_000_000_000, Pubkey::new_unique());
+        let result = wallet.check(&instructions, static_lookup_table_addresses());
+        assert_violation(result, "not owned by the signer");
+    }
+
+    #[test]
+    fn rejects_swap_owned_by_another_wallet() {
+        let wallet = Wallet::new();
+        let mut instructions = wallet.buy(100_000_000);
+        instructions[4] = swap_base_in(
+            100_000_000,
+            wallet.wsol_token_account(),
+            wallet.token_account(),
+            Pubkey::new_unique(),
+        );
+        let result = wallet.check(&instructions, static_lookup_table_addresses());
+        assert_violation(result, "swap owner is not the signer");
+    }
+
+    #[test]
+    fn allows_buy_with_priority_fee_under_the_cap() {
+        let wallet = Wallet::new();
+        let instructions = with_compute_budget(130_000, 70_000, wallet.buy(300_000_000));
+        let result = wallet.check(&instructions, static_lookup_table_addresses());
+        assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[test]
+    fn rejects_compute_unit_price_over_the_cap() {
+        let wallet = Wallet::new();
+        let instructions = with_compute_budget(u64::MAX, 70_000, wallet.buy(1_000));
+        let result = wallet.check(&instructions, static_lookup_table_addresses());
+        assert_violation(result, "compute unit price");
+    }
+
+    #[test]
+    fn rejects_compute_unit_limit_over_the_cap() {
+        let wallet = Wallet::new();
+        let instructions = with_compute_budget(130_000, 1_400_000, wallet.buy(1_000));
+        let result = wallet.check(&instructions, static_lookup_table_addresses());
+        assert_violation(result, "compute unit limit");
+    }
+
+    #[test]
+    fn counts_priority_fee_toward_the_cap() {
+        let wallet = Wallet::new();
+        // 200k units at 1 lamport each leave no room for a 499.9M lamports swap
+        let instructions = with_compute_budget(MAX_CU_PRICE, MAX_CU_LIMIT, wallet.buy(499_900_000));
+        let result = wallet.check(&instructions, static_lookup_table_addresses());
+        assert_violation(
+            result,
+            "500100000 lamports, above the 500000000 lamports cap",
+        );
+    }
+
+    #[test]
+    fn counts_priority_fee_of_the_maximum_limit_when_unset() {
+        let wallet = Wallet::new();
+        let mut instructions = wallet.buy(1_000);
+        instructions.insert(
+            0,
+            ComputeBudgetInstruction::set_compute_unit_price(MAX_CU_PRICE),
+        );
+        let result =
+            wallet.check_with_cap(1_000_000, &instructions, static_lookup_table_addresses());
+        assert_violation(result, "1401000 lamports");
+    }
+
+    #[test]
+    fn rejects_other_compute_budget_instructions() {
+        let wallet = Wallet::new();
+        let mut instructions = wallet.buy(1_000);
+        instructions.insert(0, ComputeBudgetInstruction::request_heap_frame(256 * 1024));
+        let result = wallet.check(&instructions, static_lookup_table_addresses());
+        assert_violation(result, "compute budget instruction other than");
+    }
+}
@@ -0,0 +1,256 @@
+use std::{
+    fs::Permissions,
+    os::unix::fs::{FileTypeExt, PermissionsExt},
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    bs58, message::VersionedMessage, program_utils::limited_deserialize, pubkey::Pubkey,
+    signature::Signature,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+};
+
+use crate::signer::{SignerError, TransactionSigner};
+
+/// Longest request or response line read from the socket, a serialized
+/// transaction is at most 1232 bytes before base58 encoding
+const MAX_LINE_BYTES: u64 = 8 * 1024;
+/// Time a signing service has to answer a request, so a hung service fails
+/// the swap instead of blocking the swapper
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Request sent to the signing service, one JSON object per line over a Unix
+/// socket or as the body of a POST over HTTP.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignRequest {
+    /// Base58 encoded pubkey the message should be signed with
+    pub pubkey: String,
+    /// Base58 encoded serialized message
+    pub message: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SignResponse {
+    /// Base58 encoded signature, absent if the service refused to sign
+    pub signature: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+enum Transport {
+    Http(String),
+    Unix(String),
+}
+
+/// Signs by calling a signing service, so the key never enters the bot
+/// process. The returned signature is verified against the expected pubkey.
+pub struct RemoteSigner {
+    transport: Transport,
+    pubkey: Pubkey,
+    http_client: reqwest::Client,
+}
+
+impl RemoteSigner {
+    pub fn new(url: &str, pubkey: Pubkey) -> Result<Self, SignerError> {
+        let transport = if url.starts_with("http://") || url.starts_with("https://") {
+            Transport::Http(url.to_string())
+        } else if let Some(path) = url.strip_prefix("unix:") {
+            Transport::Unix(path.to_string())
+        } else {
+            return Err(SignerError::InvalidRemoteUrl(url.to_string()));
+        };
+
+        let http_client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| SignerError::Remote(e.to_string()))?;
+
+        Ok(Self {
+            transport,
+            pubkey,
+            http_client,
+        })
+    }
+
+    async fn request(&self, request: &SignRequest) -> Result<SignResponse, SignerError> {
+        match &self.transport {
+            Transport::Http(url) => self
+                .http_client
+                .post(url)
+                .json(request)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(http_error)?
+                .json::<SignResponse>()
+                .await
+                .map_err(http_error),
+            Transport::Unix(path) => tokio::time::timeout(REQUEST_TIMEOUT, async {
+                let stream = UnixStream::connect(path)
+                    .await
+                    .map_err(|e| SignerError::Remote(e.to_string()))?;
+                let mut stream = BufReader::new(stream);
+                write_json_line(stream.get_mut(), request).await?;
+                read_json_line(&mut stream).await
+            })
+            .await
+            .map_err(|_| SignerError::Timeout(REQUEST_TIMEOUT))?,
+        }
+    }
+}
+
+fn http_error(error: reqwest::Error) -> SignerError {
+    if error.is_timeout() {
+        SignerError::Timeout(REQUEST_TIMEOUT)
+    } else {
+        SignerError::Remote(error.to_string())
+    }
+}
+
+#[async_trait]
+impl TransactionSigner for RemoteSigner {
+    fn pubkey(&self) -> Pubkey {
+        self.pubkey
+    }
+
+    async fn sign_message(&self, message: &VersionedMessage) -> Result<Signature, SignerError> {
+        let message_bytes = message.serialize();
+        let response = self
+            .request(&SignRequest {
+                pubkey: self.pubkey.to_string(),
+                message: bs58::encode(&message_bytes).into_string(),
+            })
+            .await?;
+
+        let signature = match (response.signature, response.error) {
+            (Some(signature), _) => {
+                Signature::from_str(&signature).map_err(|_| SignerError::InvalidSignature)?
+            }
+            (None, Some(error)) => return Err(SignerError::Remote(error)),
+            (None, None) => return Err(SignerError::InvalidSignature),
+        };
+
+        if !signature.verify(self.pubkey.as_ref(), &message_bytes) {
+            return Err(SignerError::InvalidSignature);
+        }
+        Ok(signature)
+    }
+}
+
+/// Serves sign requests on a Unix socket with the given signer, which should
+/// enforce a policy, e.g. a [`LocalSigningService`](super::policy::LocalSigningService).
+/// A socket left at `path` by a previous run is replaced, anything else is
+/// left alone. Only the owner of the process can connect.
+pub async fn serve_unix_socket(
+    path: &Path,
+    signer: Arc<dyn TransactionSigner>,
+) -> Result<(), SignerError> {
+    let io_error = |source| SignerError::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            std::fs::remove_file(path).map_err(io_error)?
+        }
+        Ok(_) => return Err(SignerError::NotASocket(path.to_path_buf())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(io_error(e)),
+    }
+    let listener = UnixListener::bind(path).map_err(io_error)?;
+    std::fs::set_permissions(path, Permissions::from_mode(0o600)).map_err(io_error)?;
+
+    loop {
+        let (stream, _) = listener.accept().await.map_err(io_error)?;
+        let signer = Arc::clone(&signer);
+        tokio::spawn(async move {
+            let mut stream = BufReader::new(stream);
+            let response = match read_json_line::<SignRequest>(&mut stream).await {
+                Ok(request) => handle_sign_request(signer.as_ref(), request).await,
+                Err(e) => SignResponse {
+                    error: Some(e.to_string()),
+                    ..SignResponse::default()
+                },
+            };
+            let _ = write_json_line(stream.get_mut(), &response)
+                .await
+                .inspect_err(|e| tracing::warn!("failed to answer sign request: {}", e));
+        });
+    }
+}
+
+async fn handle_sign_request(signer: &dyn TransactionSigner, request: SignRequest) -> SignResponse {
+    if request.pubkey != signer.pubkey().to_string() {
+        return SignResponse {
+            error: Some(format!("unknown pubkey {}", request.pubkey)),
+            ..SignResponse::default()
+        };
+    }
+
+    let message = bs58::decode(&request.message)
+        .into_vec()
+        .ok()
+        .and_then(|bytes| limited_deserialize::<VersionedMessage>(&bytes).ok());
+    let Some(message) = message else {
+        return SignResponse {
+            error: Some("invalid message".to_string()),
+            ..SignResponse::default()
+        };
+    };
+
+    match signer.sign_message(&message).await {
+        Ok(signature) => {
+            tracing::info!("signed message for {}", request.pubkey);
+            SignResponse {
+                signature: Some(signature.to_string()),
+                error: None,
+            }
+        }
+        Err(e) => {
+            tracing::warn!("refused to sign: {}", e);
+            SignResponse {
+                signature: None,
+                error: Some(e.to_string()),
+            }
+        }
+    }
+}
+
+async fn write_json_line<T: Serialize>(
+    stream: &mut UnixStream,
+    value: &T,
+) -> Result<(), SignerError> {
+    // Serializing plain string fields cannot fail
+    let mut line = serde_json::to_vec(value).unwrap();
+    line.push(b'\n');
+    stream
+        .write_all(&line)
+        .await
+        .map_err(|e| SignerError::Remote(e.to_string()))
+}
+
+async fn read_json_line<T: serde::de::DeserializeOwned>(
+    stream: &mut BufReader<UnixStream>,
+) -> Result<T, SignerError> {
+    let mut line = String::new();
+    let read = stream
+        .take(MAX_LINE_BYTES)
+        .read_line(&mut line)
+        .await
+        .map_err(|e| SignerError::Remote(e.to_string()))?;
+    if read as u64 == MAX_LINE_BYTES && !line.ends_with('\n') {
+        return Err(SignerError::Remote(format!(
+            "line longer than {} bytes",
+            MAX_LINE_BYTES
+        )));
+    }
+    serde_json::from_str(&line).map_err(|e| SignerError::Remote(e.to_string()))
+}
@@ -1,10 +1,36 @@
+use std::path::PathBuf;
+
 use clap::{Parser, Subcommand};
 
+pub mod backtest;
 pub mod instant_swap;
+pub mod journal;
+pub mod keystore;
 pub mod listen;
+pub mod lookup_table;
+pub mod nonce;
+pub mod quote;
+pub mod reclaim_rent;
+pub mod replay;
+pub mod sell_all;
+pub mod signing_service;
+pub mod wallets;
 
+use backtest::BacktestSubcommand;
 use instant_swap::InstantSwapSubcommand;
+use journal::JournalSubcommand;
+use keystore::KeystoreSubcommand;
 use listen::ListenSubcommand;
+use lookup_table::LookupTableSubcommand;
+use nonce::NonceSubcommand;
+use quote::QuoteSubcommand;
+use reclaim_rent::ReclaimRentSubcommand;
+use replay::ReplaySubcommand;
+use sell_all::SellAllSubcommand;
+use signing_service::SigningServiceSubcommand;
+use wallets::WalletsSubcommand;
+
+use crate::utils::{LogFormat, LogRotation};
 
 /// Buy and sell memecoins
 #[derive(Parser, Debug)]
@@ -12,10 +38,33 @@ use listen::ListenSubcommand;
 pub struct Args {
     #[clap(subcommand)]
     pub command: Subcommands,
+
+    /// Format of the log lines
+    #[arg(long, global = true, value_enum, default_value = "text")]
+    pub log_format: LogFormat,
+
+    /// Also write logs to files in this directory
+    #[arg(long, global = true)]
+    pub log_dir: Option<PathBuf>,
+
+    /// How often log files are rotated
+    #[arg(long, global = true, value_enum, default_value = "daily")]
+    pub log_rotation: LogRotation,
 }
 
 #[derive(Debug, Subcommand)]
 pub enum Subcommands {
+    Backtest(BacktestSubcommand),
     InstantSwap(InstantSwapSubcommand),
+    Journal(JournalSubcommand),
+    Keystore(KeystoreSubcommand),
     Listen(ListenSubcommand),
+    LookupTable(LookupTableSubcommand),
+    Nonce(NonceSubcommand),
+    Quote(QuoteSubcommand),
+    ReclaimRent(ReclaimRentSubcommand),
+    Replay(ReplaySubcommand),
+    SellAll(SellAllSubcommand),
+    SigningService(SigningServiceSubcommand),
+    Wallets(WalletsSubcommand),
 }
@@ -0,0 +1,142 @@
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+
+use crate::{
+    actors::swapper::strategy::Strategy,
+    constants::LAMPORTS_PER_SOL,
+    recording::{
+        backtest::BacktestStats,
+        replay::{replay_recordings, ReplayConfig},
+    },
+};
+
+#[derive(Debug, Args)]
+pub struct BacktestSubcommand {
+    /// Recordings written by `listen --record`
+    #[arg(required = true)]
+    pub recordings: Vec<PathBuf>,
+
+    /// Take profit multiples of the buy price to try
+    #[arg(long, value_delimiter = ',', default_value = "2")]
+    pub take_profit: Vec<f64>,
+
+    /// Minimum SOL vault balances to try, in SOL
+    #[arg(long, value_delimiter = ',', default_value = "25")]
+    pub min_liquidity: Vec<f64>,
+
+    /// Maximum SOL vault balances to try, in SOL
+    #[arg(long, value_delimiter = ',', default_value = "150")]
+    pub max_liquidity: Vec<f64>,
+
+    /// Price checks before giving up on a position to try
+    #[arg(long, value_delimiter = ',', default_value = "100")]
+    pub max_price_checks: Vec<u32>,
+
+    /// Input max swappers
+    #[arg(short, long, default_value = "1")]
+    pub max_swappers: usize,
+
+    /// Input trade amount
+    #[arg(short, long, default_value = "0.001")]
+    pub trade_amount: f64,
+
+    /// Starting virtual SOL balance
+    #[arg(long, default_value_t = 10.)]
+    pub paper_sol: f64,
+
+    /// Also write one CSV row per configuration to this file
+    #[arg(long)]
+    pub csv: Option<PathBuf>,
+}
+
+impl BacktestSubcommand {
+    pub async fn run(self) {
+        let mut results = vec![];
+        for strategy in self.strategies() {
+            let config = ReplayConfig {
+                strategy,
+                trade_amount: self.trade_amount,
+                max_swappers: self.max_swappers,
+                paper_sol: self.paper_sol,
+            };
+            let trades = replay_recordings(&self.recordings, config, 0.)
+                .await
+                .expect("failed to replay");
+            results.push(BacktestStats::from_trades(&strategy, &trades));
+        }
+
+        print_results(&results);
+
+        if let Some(path) = &self.csv {
+            write_csv(path, &results).expect("failed to write csv");
+            tracing::info!("wrote {} configurations to {:?}", results.len(), path);
+        }
+    }
+
+    /// Every combination of the given thresholds
+    fn strategies(&self) -> Vec<Strategy> {
+        let mut strategies = vec![];
+        for &take_profit_multiple in &self.take_profit {
+            for &min_liquidity in &self.min_liquidity {
+                for &max_liquidity in &self.max_liquidity {
+                    for &max_price_checks in &self.max_price_checks {
+                        strategies.push(Strategy {
+                            min_liquidity: (min_liquidity * *LAMPORTS_PER_SOL) as u64,
+                            max_liquidity: (max_liquidity * *LAMPORTS_PER_SOL) as u64,
+                            take_profit_multiple,
+                            max_price_checks,
+                        });
+                    }
+                }
+            }
+        }
+        strategies
+    }
+}
+
+fn print_results(results: &[BacktestStats]) {
+    println!(
+        "{:>6} {:>9} {:>9} {:>7} {:>6} {:>7} {:>7} {:>9} {:>9} {:>12} {:>12} {:>8} {:>8}",
+        "tp",
+        "min liq",
+        "max liq",
+        "checks",
+        "pools",
+        "entered",
+        "win %",
+        "avg %",
+        "median %",
+        "PnL SOL",
+        "max dd SOL",
+        "p50 s",
+        "p90 s"
+    );
+    for stats in results {
+        println!(
+            "{:>6.2} {:>9.1} {:>9.1} {:>7} {:>6} {:>7} {:>7.1} {:>9.2} {:>9.2} {:>12.6} {:>12.6} {:>8.0} {:>8.0}",
+            stats.take_profit_multiple,
+            stats.min_liquidity_sol,
+            stats.max_liquidity_sol,
+            stats.max_price_checks,
+            stats.pools,
+            stats.entered,
+            stats.win_rate_pct,
+            stats.average_return_pct,
+            stats.median_return_pct,
+            stats.pnl_sol,
+            stats.max_drawdown_sol,
+            stats.time_in_trade_p50_s,
+            stats.time_in_trade_p90_s,
+        );
+    }
+}
+
+fn write_csv(path: &Path, results: &[BacktestStats]) -> Result<(), eyre::Error> {
+    let mut writer = csv::Writer::from_path(path)?;
+    for stats in results {
+        writer.serialize(stats)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -1,10 +1,19 @@
-use std::{str::FromStr, sync::Arc};
+use std::{collections::BTreeMap, str::FromStr, sync::Arc};
 
 use clap::Args;
+use serde::Serialize;
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::pubkey::Pubkey;
 
-use crate::{actors::swapper::actor::Swapper, types::ProgramConfig, utils::get_market_id};
+use crate::{
+    actors::swapper::actor::Swapper,
+    constants::LAMPORTS_PER_SOL,
+    journal::Journal,
+    sender::{lookup_table::load_configured_lookup_tables, paper::PaperEngine},
+    signer::TransactionSigner,
+    types::ProgramConfig,
+    utils::{find_market_id, get_wallet_token_accounts},
+};
 
 #[derive(Debug, Args)]
 pub struct InstantSwapSubcommand {
@@ -17,43 +26,211 @@ pub struct InstantSwapSubcommand {
     pub output_token_address: String,
 
     /// Amount in decimals in
-    #[arg(short, long)]
-    pub amount_in: f64,
+    #[arg(short, long, required_unless_present = "sell_only")]
+    pub amount_in: Option<f64>,
+
+    /// Stop after the buy, without selling or handing over to the exit strategy
+    #[arg(long, conflicts_with_all = ["sell_only", "sell_pct", "exit_strategy"])]
+    pub buy_only: bool,
+
+    /// Skip the buy and sell the output token balance already held
+    #[arg(long, conflicts_with = "exit_strategy")]
+    pub sell_only: bool,
+
+    /// Percentage of the output token balance to sell back, 100 with `--sell-only`
+    #[arg(long)]
+    pub sell_pct: Option<f64>,
+
+    /// Maximum slippage below the pool quote on the sell, in basis points
+    #[arg(long, default_value_t = 2_500)]
+    pub slippage_bps: u64,
+
+    /// Hand the position over to the listener exit strategy after the buy
+    #[arg(long, conflicts_with = "sell_pct")]
+    pub exit_strategy: bool,
+
+    /// Simulate fills against the pool reserves instead of sending transactions
+    #[arg(long, conflicts_with = "sell_only")]
+    pub paper: bool,
+
+    /// Starting virtual SOL balance in paper mode
+    #[arg(long, default_value_t = 10.)]
+    pub paper_sol: f64,
+}
+
+/// Outcome of the subcommand, printed as JSON on stdout
+#[derive(Debug, Default, Serialize)]
+struct InstantSwapReport {
+    wallet: String,
+    /// Fills were simulated, balances are the virtual ones
+    paper: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    buy: Option<SwapReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sell: Option<SwapReport>,
+    /// SOL balance and token balances by mint, in decimals, tokens are in
+    /// base units in paper mode
+    balances: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct SwapReport {
+    input_token: String,
+    /// Amount swapped, in base units, unknown after an exit strategy sell
+    #[serde(skip_serializing_if = "Option::is_none")]
+    amount_in: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature: Option<String>,
 }
 
 impl InstantSwapSubcommand {
-    pub async fn run(self, client: Arc<RpcClient>, config: ProgramConfig) {
-        let market_id = get_market_id(
-            &client,
-            &self.input_token_address,
-            &self.output_token_address,
-        )
-        .await;
+    pub async fn run(
+        self,
+        client: Arc<RpcClient>,
+        config: ProgramConfig,
+        signer: Arc<dyn TransactionSigner>,
+    ) {
+        let mut report = InstantSwapReport {
+            wallet: signer.pubkey().to_string(),
+            paper: self.paper,
+            ..Default::default()
+        };
+        let paper_engine = self.paper.then(|| {
+            Arc::new(PaperEngine::new(
+                (self.paper_sol * *LAMPORTS_PER_SOL) as u64,
+            ))
+        });
 
-        let swapper = Swapper::new(client, config, market_id, self.amount_in)
-            .await
-            .expect("failed to swap");
-        swapper
+        if let Err(e) = self
             .swap(
-                &Pubkey::from_str(&self.input_token_address)
-                    .expect("Enter correct input token address"),
-                self.amount_in,
+                &client,
+                config,
+                Arc::clone(&signer),
+                paper_engine.clone(),
+                &mut report,
             )
             .await
-            .expect("failed to swap");
+        {
+            tracing::error!("failed to swap: {:?}", e);
+            report.error = Some(e.to_string());
+        }
 
-        tracing::info!("sell how much?");
-        let mut amount = String::new();
-        let _ = std::io::stdin().read_line(&mut amount).unwrap();
-        let amount_in: f64 = amount.trim().parse().unwrap();
+        let balances = match &paper_engine {
+            Some(paper_engine) => Ok(get_paper_balances(paper_engine)),
+            None => get_balances(&client, &signer.pubkey()).await,
+        };
+        match balances {
+            Ok(balances) => report.balances = balances,
+            Err(e) => tracing::error!("failed to get balances: {:?}", e),
+        }
 
-        swapper
-            .swap(
-                &Pubkey::from_str(&self.output_token_address)
-                    .expect("Enter correct output token address"),
-                amount_in,
-            )
-            .await
-            .expect("failed to swap");
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).expect("failed to serialize report")
+        );
+        if report.error.is_some() {
+            std::process::exit(1);
+        }
+    }
+
+    async fn swap(
+        &self,
+        client: &Arc<RpcClient>,
+        config: ProgramConfig,
+        signer: Arc<dyn TransactionSigner>,
+        paper_engine: Option<Arc<PaperEngine>>,
+        report: &mut InstantSwapReport,
+    ) -> Result<(), eyre::Error> {
+        let input_token = Pubkey::from_str(&self.input_token_address)
+            .map_err(|_| eyre::eyre!("invalid input token address"))?;
+        let output_token = Pubkey::from_str(&self.output_token_address)
+            .map_err(|_| eyre::eyre!("invalid output token address"))?;
+
+        let market_id = find_market_id(
+            client,
+            &self.input_token_address,
+            &self.output_token_address,
+        )
+        .await?
+        .ok_or_else(|| eyre::eyre!("no market for this pair"))?;
+
+        let amount_in = self.amount_in.unwrap_or_default();
+        let journal = Journal::from_config(&config);
+        let lookup_tables = load_configured_lookup_tables(client, &config).await?;
+        let mut swapper = Swapper::new(Arc::clone(client), config, signer, market_id, amount_in)
+            .await?
+            .with_paper_engine(paper_engine)
+            .with_journal(journal)
+            .with_lookup_tables(lookup_tables);
+
+        if !self.sell_only {
+            let signature = swapper.swap(&input_token, amount_in).await?;
+            report.buy = Some(SwapReport {
+                input_token: input_token.to_string(),
+                amount_in: Some(swapper.to_base_units(&input_token, amount_in)),
+                signature: Some(signature.to_string()),
+            });
+        }
+
+        if self.buy_only {
+            return Ok(());
+        }
+
+        if self.exit_strategy {
+            let signature = swapper.run_exit_strategy().await?;
+            report.sell = Some(SwapReport {
+                input_token: output_token.to_string(),
+                amount_in: None,
+                signature: signature.map(|signature| signature.to_string()),
+            });
+            return Ok(());
+        }
+
+        let sell_pct = match self.sell_pct {
+            Some(sell_pct) => sell_pct,
+            None if self.sell_only => 100.,
+            None => return Ok(()),
+        };
+        let (amount_in, signature) = swapper
+            .swap_pct(&output_token, sell_pct, self.slippage_bps)
+            .await?;
+        report.sell = Some(SwapReport {
+            input_token: output_token.to_string(),
+            amount_in: Some(amount_in),
+            signature: Some(signature.to_string()),
+        });
+        Ok(())
+    }
+}
+
+async fn get_balances(
+    client: &RpcClient,
+    owner: &Pubkey,
+) -> Result<BTreeMap<String, String>, eyre::Error> {
+    let mut balances = BTreeMap::new();
+    let lamports = client.get_balance(owner).await?;
+    balances.insert(
+        "SOL".to_string(),
+        (lamports as f64 / *LAMPORTS_PER_SOL).to_string(),
+    );
+    for token_account in get_wallet_token_accounts(client, owner).await? {
+        balances.insert(token_account.mint.to_string(), token_account.ui_amount);
+    }
+    Ok(balances)
+}
+
+/// Virtual SOL balance and token balances by mint, in base units
+fn get_paper_balances(paper_engine: &PaperEngine) -> BTreeMap<String, String> {
+    let book = paper_engine.book();
+    let mut balances = BTreeMap::new();
+    balances.insert(
+        "SOL".to_string(),
+        (book.lamports as f64 / *LAMPORTS_PER_SOL).to_string(),
+    );
+    for (mint, amount) in book.tokens {
+        balances.insert(mint.to_string(), amount.to_string());
     }
+    balances
 }
@@ -0,0 +1,114 @@
+use std::{path::Path, str::FromStr};
+
+use chrono::{DateTime, NaiveDate};
+use clap::Args;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    constants::LAMPORTS_PER_SOL,
+    journal::{Journal, JournalEntry, JournalQuery, TradeOutcome, TradeSide},
+    types::ProgramConfig,
+};
+
+#[derive(Debug, Args)]
+pub struct JournalSubcommand {
+    /// First day to show, UTC `YYYY-MM-DD`
+    #[arg(long)]
+    pub from: Option<NaiveDate>,
+
+    /// Last day to show, UTC `YYYY-MM-DD`
+    #[arg(long)]
+    pub to: Option<NaiveDate>,
+
+    /// Only show swaps of this token
+    #[arg(short, long)]
+    pub mint: Option<String>,
+
+    #[arg(long, value_enum)]
+    pub side: Option<TradeSide>,
+
+    /// Only show sells that realized a profit or a loss
+    #[arg(long, value_enum)]
+    pub outcome: Option<TradeOutcome>,
+
+    /// Print the entries as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+impl JournalSubcommand {
+    pub fn run(self, config: ProgramConfig) {
+        let Some(path) = &config.journal_db_path else {
+            tracing::error!("JOURNAL_DB_PATH is not set");
+            std::process::exit(1);
+        };
+        let journal = Journal::open(Path::new(path)).expect("failed to open journal database");
+
+        let mint = self
+            .mint
+            .as_deref()
+            .map(|mint| Pubkey::from_str(mint).expect("invalid mint address"));
+        let entries = journal
+            .query(&JournalQuery {
+                from: self.from,
+                to: self.to,
+                mint,
+                side: self.side,
+                outcome: self.outcome,
+            })
+            .expect("failed to query journal");
+
+        if self.json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&entries).expect("failed to serialize entries")
+            );
+        } else {
+            print_entries(&entries);
+        }
+    }
+}
+
+fn print_entries(entries: &[JournalEntry]) {
+    println!(
+        "{:<19} {:<4} {:<44} {:>20} {:>20} {:>11} {:>12}  signature",
+        "time", "side", "mint", "in", "out", "fees SOL", "PnL SOL"
+    );
+    let mut fee_lamports = 0;
+    let mut pnl_lamports = 0;
+    for entry in entries {
+        let entry_fee_lamports =
+            entry.base_fee_lamports + entry.priority_fee_lamports + entry.tip_lamports;
+        fee_lamports += entry_fee_lamports;
+        pnl_lamports += entry.realized_pnl_lamports.unwrap_or_default();
+        println!(
+            "{:<19} {:<4} {:<44} {:>20} {:>20} {:>11.6} {:>12}  {}",
+            DateTime::from_timestamp_millis(entry.at_ms as i64)
+                .map(|at| at.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default(),
+            match entry.side {
+                TradeSide::Buy => "buy",
+                TradeSide::Sell => "sell",
+            },
+            entry.mint,
+            entry.amount_in,
+            entry.amount_out,
+            entry_fee_lamports as f64 / *LAMPORTS_PER_SOL,
+            entry
+                .realized_pnl_lamports
+                .map(|pnl| format!("{:.6}", pnl as f64 / *LAMPORTS_PER_SOL))
+                .unwrap_or_else(|| "-".to_string()),
+            entry.signature
+        );
+    }
+    println!(
+        "{:<19} {:<4} {:<44} {:>20} {:>20} {:>11.6} {:>12.6}",
+        "total",
+        "",
+        format!("{} swaps", entries.len()),
+        "",
+        "",
+        fee_lamports as f64 / *LAMPORTS_PER_SOL,
+        pnl_lamports as f64 / *LAMPORTS_PER_SOL
+    );
+}
@@ -0,0 +1,35 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use solana_sdk::{signature::Keypair, signer::Signer};
+
+use crate::signer::keystore::{encrypt_keystore_file, read_passphrase};
+
+#[derive(Debug, Args)]
+pub struct KeystoreSubcommand {
+    /// Path of the keystore file to write
+    #[arg(short, long)]
+    pub output: PathBuf,
+}
+
+impl KeystoreSubcommand {
+    /// Encrypts the configured buyer keypair into a keystore file
+    pub fn run(self, user_keypair: Keypair) {
+        let passphrase =
+            read_passphrase("New keystore passphrase: ").expect("failed to read passphrase");
+        let confirmation =
+            read_passphrase("Confirm passphrase: ").expect("failed to read passphrase");
+        if passphrase != confirmation {
+            tracing::error!("passphrases do not match");
+            return;
+        }
+
+        encrypt_keystore_file(&self.output, &user_keypair, &passphrase)
+            .expect("failed to write keystore");
+        tracing::info!(
+            "wrote keystore for {} to {:?}, set BUYER_KEYSTORE_PATH to use it",
+            user_keypair.pubkey(),
+            self.output
+        );
+    }
+}
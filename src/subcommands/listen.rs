@@ -1,14 +1,39 @@
-use std::sync::Arc;
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    pin::pin,
+    sync::Arc,
+    time::Instant,
+};
 
 use clap::Args;
-use coerce::actor::{system::ActorSystem, IntoActor};
+use coerce::actor::{system::ActorSystem, IntoActor, LocalActorRef};
+use futures_util::future::select;
 use once_cell::sync::Lazy;
 use solana_client::nonblocking::rpc_client::RpcClient;
-use tokio::sync::Notify;
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::Notify,
+};
 
 use crate::{
-    actors::{guard::GuardActor, listener::actor::Listener},
+    actors::{
+        guard::GuardActor,
+        listener::actor::{Listener, Shutdown, ShutdownPolicy},
+    },
+    constants::LAMPORTS_PER_SOL,
+    control,
+    metrics::{
+        self, OPEN_POSITIONS, POOLS_DETECTED, REALIZED_PNL_SOL, SWAPPERS_SPAWNED, SWAPS_FAILED,
+        SWAPS_LANDED, UNREALIZED_PNL_SOL,
+    },
+    positions::PositionStore,
+    recording::recorder::Recorder,
+    sender::{lookup_table::load_configured_lookup_tables, paper::PaperEngine},
+    signer::TransactionSigner,
+    tui,
     types::ProgramConfig,
+    utils::get_epoch,
 };
 
 static NOTIFY: Lazy<Arc<Notify>> = Lazy::new(|| Arc::new(Notify::new()));
@@ -23,16 +48,119 @@ pub struct ListenSubcommand {
     #[arg(short, long)]
     #[arg(default_value = "0.001")]
     trade_amount: f64,
+    /// Simulate fills against the pool reserves instead of sending transactions
+    #[arg(long)]
+    paper: bool,
+    /// Starting virtual SOL balance in paper mode
+    #[arg(long, default_value_t = 10.)]
+    paper_sol: f64,
+    /// Record detected pools and their vault balances to this gzip file,
+    /// see `replay` subcommand
+    #[arg(long)]
+    record: Option<PathBuf>,
+    /// Serve Prometheus metrics on `http://<addr>/metrics`
+    #[arg(long)]
+    metrics_addr: Option<SocketAddr>,
+    /// Serve the JSON control API on `http://<addr>`, bind it to localhost
+    #[arg(long)]
+    control_addr: Option<SocketAddr>,
+    /// Show a live dashboard instead of the console logs, see `--log-dir`
+    #[arg(long)]
+    pub tui: bool,
+    /// What becomes of the open positions on SIGINT or SIGTERM, a second
+    /// signal exits right away
+    #[arg(long, value_enum, default_value = "drain")]
+    on_shutdown: ShutdownPolicy,
 }
 
 impl ListenSubcommand {
-    pub async fn run(self, client: Arc<RpcClient>, config: ProgramConfig) {
-        let system = ActorSystem::new();
+    pub async fn run(
+        self,
+        client: Arc<RpcClient>,
+        config: ProgramConfig,
+        wallets: Vec<Arc<dyn TransactionSigner>>,
+    ) {
+        let started_at = Instant::now();
+        if let Some(addr) = self.metrics_addr {
+            tokio::task::spawn(async move {
+                if let Err(e) = metrics::serve(addr).await {
+                    tracing::error!("failed to serve metrics: {:?}", e);
+                }
+            });
+        }
 
-        let listener = Listener::new(client, config, self.max_swappers, self.trade_amount)
-            .into_actor(Some("listener".to_string()), &system)
+        let recorder = self
+            .record
+            .as_deref()
+            .map(|path| Arc::new(Recorder::create(path).expect("failed to create recording")));
+
+        // Paper positions only live as long as the virtual book
+        let position_store = match &config.positions_db_path {
+            Some(_) if self.paper => {
+                tracing::warn!("positions are not persisted in paper mode");
+                None
+            }
+            Some(path) => Some(Arc::new(
+                PositionStore::open(Path::new(path)).expect("failed to open positions database"),
+            )),
+            None => None,
+        };
+        let positions_persisted = position_store.is_some();
+
+        // Both are needed by every swap, fetching them up front keeps them
+        // off the path to the first buy
+        let lookup_tables = load_configured_lookup_tables(&client, &config)
             .await
-            .expect("failed to start listener");
+            .expect("failed to load lookup table");
+        get_epoch(&client).await.expect("failed to get epoch");
+
+        let system = ActorSystem::new();
+
+        let listener = Listener::new(
+            client,
+            config,
+            wallets,
+            self.max_swappers,
+            self.trade_amount,
+            self.paper.then(|| {
+                Arc::new(PaperEngine::new(
+                    (self.paper_sol * *LAMPORTS_PER_SOL) as u64,
+                ))
+            }),
+            recorder.clone(),
+        )
+        .with_position_store(position_store)
+        .with_lookup_tables(lookup_tables)
+        .with_exit_notification(NOTIFY.clone())
+        .into_actor(Some("listener".to_string()), &system)
+        .await
+        .expect("failed to start listener");
+
+        if let Some(addr) = self.control_addr {
+            let listener = listener.clone();
+            tokio::task::spawn(async move {
+                if let Err(e) = control::serve(addr, listener).await {
+                    tracing::error!("failed to serve control API: {:?}", e);
+                }
+            });
+        }
+
+        let signals_listener = listener.clone();
+        let policy = self.on_shutdown;
+        tokio::task::spawn(async move {
+            if let Err(e) = shutdown_on_signals(signals_listener, policy).await {
+                tracing::error!("failed to handle shutdown signals: {:?}", e);
+            }
+        });
+
+        let dashboard = self.tui.then(|| {
+            let listener = listener.clone();
+            tokio::task::spawn(async move {
+                if let Err(e) = tui::run(listener, policy).await {
+                    tracing::error!("dashboard failed: {:?}", e);
+                }
+            })
+        });
 
         let guard = GuardActor::new(listener, NOTIFY.clone());
 
@@ -43,5 +171,79 @@ impl ListenSubcommand {
 
         NOTIFY.notified().await;
         guard.stop().await.expect("failed to stop guard");
+        if let Some(dashboard) = dashboard {
+            // Gives the terminal back
+            let _ = dashboard.await;
+        }
+
+        if let Some(recorder) = recorder {
+            if let Err(e) = recorder.finish() {
+                tracing::error!("failed to finish recording: {:?}", e);
+            }
+        }
+
+        print_summary(started_at, positions_persisted);
+    }
+}
+
+/// Shuts the listener down with `policy` on SIGINT or SIGTERM, and with
+/// [`ShutdownPolicy::Persist`] on the next one
+async fn shutdown_on_signals(
+    listener: LocalActorRef<Listener>,
+    mut policy: ShutdownPolicy,
+) -> Result<(), eyre::Error> {
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let mut terminate = signal(SignalKind::terminate())?;
+    loop {
+        select(pin!(interrupt.recv()), pin!(terminate.recv())).await;
+        tracing::info!("received shutdown signal, shutting down with {:?}", policy);
+        listener.send(Shutdown(policy)).await?;
+        if policy == ShutdownPolicy::Persist {
+            return Ok(());
+        }
+        tracing::info!("signal again to exit right away");
+        policy = ShutdownPolicy::Persist;
+    }
+}
+
+/// Prints what the run did once the listener is done
+fn print_summary(started_at: Instant, positions_persisted: bool) {
+    let secs = started_at.elapsed().as_secs();
+    println!(
+        "listened for {}:{:02}:{:02}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    );
+    println!("{:<17} {}", "pools detected", POOLS_DETECTED.get());
+    println!("{:<17} {}", "swappers spawned", SWAPPERS_SPAWNED.get());
+    for (label, side) in [("buys", "buy"), ("sells", "sell")] {
+        println!(
+            "{:<17} {} landed, {} failed",
+            label,
+            SWAPS_LANDED.with_label_values(&[side]).get(),
+            SWAPS_FAILED.with_label_values(&[side]).get()
+        );
+    }
+    println!("{:<17} {:+.6} SOL", "realized PnL", REALIZED_PNL_SOL.get());
+
+    let open_positions = OPEN_POSITIONS.get();
+    if open_positions == 0 {
+        println!("{:<17} 0", "open positions");
+        return;
     }
+    println!(
+        "{:<17} {}, {}",
+        "open positions",
+        open_positions,
+        match positions_persisted {
+            true => "resumed on the next start",
+            false => "not persisted, see POSITIONS_DB_PATH",
+        }
+    );
+    println!(
+        "{:<17} {:+.6} SOL",
+        "unrealized PnL",
+        UNREALIZED_PNL_SOL.get()
+    );
 }
@@ -0,0 +1,76 @@
+use std::{str::FromStr, sync::Arc};
+
+use clap::Args;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    address_lookup_table::instruction::{create_lookup_table, extend_lookup_table},
+    commitment_config::CommitmentConfig,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    sender::{
+        lookup_table::{get_address_lookup_table_accounts, static_lookup_table_addresses},
+        sign_and_send_instructions,
+    },
+    signer::TransactionSigner,
+};
+
+#[derive(Debug, Args)]
+pub struct LookupTableSubcommand {
+    /// Lookup table address to extend, a new table is created if omitted
+    #[arg(short, long)]
+    pub address: Option<String>,
+}
+
+impl LookupTableSubcommand {
+    pub async fn run(self, client: Arc<RpcClient>, payer: Arc<dyn TransactionSigner>) {
+        let (table_address, existing_addresses) = match self.address {
+            Some(address) => {
+                let address =
+                    Pubkey::from_str(&address).expect("Enter correct lookup table address");
+                let table = get_address_lookup_table_accounts(&client, &[address])
+                    .await
+                    .expect("failed to get lookup table")
+                    .pop()
+                    .unwrap();
+                (address, table.addresses)
+            }
+            None => {
+                let recent_slot = client
+                    .get_slot_with_commitment(CommitmentConfig::finalized())
+                    .await
+                    .expect("failed to get slot");
+                let (instruction, address) =
+                    create_lookup_table(payer.pubkey(), payer.pubkey(), recent_slot);
+                sign_and_send_instructions(&client, payer.as_ref(), &[instruction], &[])
+                    .await
+                    .expect("failed to create lookup table");
+                tracing::info!("created lookup table {}", address);
+                (address, vec![])
+            }
+        };
+
+        let missing_addresses: Vec<Pubkey> = static_lookup_table_addresses()
+            .into_iter()
+            .filter(|address| !existing_addresses.contains(address))
+            .collect();
+
+        if !missing_addresses.is_empty() {
+            let instruction = extend_lookup_table(
+                table_address,
+                payer.pubkey(),
+                Some(payer.pubkey()),
+                missing_addresses,
+            );
+            sign_and_send_instructions(&client, payer.as_ref(), &[instruction], &[])
+                .await
+                .expect("failed to extend lookup table");
+        }
+
+        tracing::info!(
+            "lookup table {} is up to date, set LOOKUP_TABLE_ADDRESS to use it",
+            table_address
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use std::{str::FromStr, sync::Arc};
+
+use clap::{Args, Subcommand};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    sender::nonce::{advance_nonce_account, create_nonce_account, get_nonce_data},
+    signer::TransactionSigner,
+    types::ProgramConfig,
+};
+
+#[derive(Debug, Args)]
+pub struct NonceSubcommand {
+    #[clap(subcommand)]
+    pub action: NonceAction,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum NonceAction {
+    /// Create a nonce account derived from the buyer key and a seed
+    Create {
+        /// Seed the nonce account address is derived from
+        #[arg(short, long)]
+        #[arg(default_value = "solana-buyer-nonce")]
+        seed: String,
+    },
+    /// Advance the nonce, invalidating transactions signed with the current one
+    Advance,
+    /// Print the authority and current value of the nonce
+    Show,
+}
+
+impl NonceSubcommand {
+    pub async fn run(
+        self,
+        client: Arc<RpcClient>,
+        config: ProgramConfig,
+        payer: Arc<dyn TransactionSigner>,
+    ) {
+        let nonce_account = || {
+            let address = config
+                .nonce_account_address
+                .as_deref()
+                .expect("NONCE_ACCOUNT_ADDRESS is not set");
+            Pubkey::from_str(address).expect("Enter correct nonce account address")
+        };
+
+        match self.action {
+            NonceAction::Create { seed } => {
+                let nonce_account = create_nonce_account(&client, payer.as_ref(), &seed)
+                    .await
+                    .expect("failed to create nonce account");
+                tracing::info!(
+                    "created nonce account {}, set NONCE_ACCOUNT_ADDRESS to use it",
+                    nonce_account
+                );
+            }
+            NonceAction::Advance => {
+                let signature = advance_nonce_account(&client, payer.as_ref(), &nonce_account())
+                    .await
+                    .expect("failed to advance nonce account");
+                tracing::info!("advanced nonce (sig: {:?})", signature);
+            }
+            NonceAction::Show => {
+                let nonce_data = get_nonce_data(&client, &nonce_account())
+                    .await
+                    .expect("failed to read nonce account");
+                tracing::info!(
+                    "authority: {}, nonce: {}",
+                    nonce_data.authority,
+                    nonce_data.blockhash()
+                );
+            }
+        }
+    }
+}
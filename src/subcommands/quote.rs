@@ -0,0 +1,221 @@
+use std::{str::FromStr, sync::Arc};
+
+use borsh::BorshDeserialize;
+use clap::Args;
+use serde::Serialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    types::{MintInfo, PoolInfo},
+    utils::{
+        find_market_id, get_amm_id, get_epoch, get_pool_and_market_info, get_pool_reserves,
+        get_swap_amount_out, get_swap_fee, get_token_accounts,
+    },
+};
+
+#[derive(Debug, Args)]
+pub struct QuoteSubcommand {
+    /// Input token address
+    #[arg(short, long)]
+    pub input_token_address: String,
+
+    /// Output token address, used to find the pool when no AMM id is given
+    #[arg(short, long, required_unless_present = "amm_id")]
+    pub output_token_address: Option<String>,
+
+    /// Raydium AMM v4 pool address
+    #[arg(long)]
+    pub amm_id: Option<String>,
+
+    /// Amount in decimals in
+    #[arg(short, long)]
+    pub amount_in: f64,
+
+    /// Slippage used for the minimum out, in basis points
+    #[arg(short, long, default_value_t = 100)]
+    pub slippage_bps: u64,
+
+    /// Print the quote as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Expected outcome of a swap, amounts in decimals
+#[derive(Debug, Serialize)]
+struct Quote {
+    amm_id: String,
+    input_token: String,
+    output_token: String,
+    amount_in: f64,
+    /// Token-2022 transfer fees on the way in and out
+    transfer_fee_in: f64,
+    transfer_fee_out: f64,
+    /// Pool swap fee, in input token
+    swap_fee: f64,
+    expected_out: f64,
+    minimum_out: f64,
+    slippage_bps: u64,
+    /// Loss against the spot price caused by the trade size, in percent
+    price_impact_pct: f64,
+}
+
+impl QuoteSubcommand {
+    pub async fn run(self, client: Arc<RpcClient>) {
+        match self.quote(&client).await {
+            Ok(quote) if self.json => println!(
+                "{}",
+                serde_json::to_string_pretty(&quote).expect("failed to serialize quote")
+            ),
+            Ok(quote) => print_quote(&quote),
+            Err(e) => {
+                tracing::error!("failed to quote: {:?}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    async fn quote(&self, client: &RpcClient) -> Result<Quote, eyre::Error> {
+        let input_token = Pubkey::from_str(&self.input_token_address)
+            .map_err(|_| eyre::eyre!("invalid input token address"))?;
+        let (amm_id, market_id) = self.resolve_pool(client).await?;
+        let (pool_info, _) = get_pool_and_market_info(client, &amm_id, &market_id).await?;
+
+        let base_in = if input_token == pool_info.base_mint {
+            true
+        } else if input_token == pool_info.quote_mint {
+            false
+        } else {
+            return Err(eyre::eyre!(
+                "{} is not traded in pool {}",
+                input_token,
+                amm_id
+            ));
+        };
+        let (output_token, decimals_in, decimals_out) = if base_in {
+            (
+                pool_info.quote_mint,
+                pool_info.base_decimal,
+                pool_info.quote_decimal,
+            )
+        } else {
+            (
+                pool_info.base_mint,
+                pool_info.quote_decimal,
+                pool_info.base_decimal,
+            )
+        };
+
+        let vaults =
+            get_token_accounts(client, &[pool_info.base_vault, pool_info.quote_vault]).await?;
+        // safe to unwrap, because `[get_token_accounts]` checks that returned
+        // vector length matches the input vector length
+        let base_vault_amount = vaults.first().unwrap().amount;
+        let quote_vault_amount = vaults.get(1).unwrap().amount;
+
+        let mint_accounts = client
+            .get_multiple_accounts(&[input_token, output_token])
+            .await?;
+        let mut mint_infos = get_mint_infos(&[input_token, output_token], mint_accounts)?;
+        let mint_info_out = mint_infos.pop().unwrap();
+        let mint_info_in = mint_infos.pop().unwrap();
+        let epoch = get_epoch(client).await?;
+
+        let scale_in = 10_f64.powi(decimals_in as i32);
+        let scale_out = 10_f64.powi(decimals_out as i32);
+        let amount_in = (self.amount_in * scale_in) as u64;
+        let transfer_fee_in = mint_info_in.transfer_fee(amount_in, epoch);
+        let pool_amount_in = amount_in - transfer_fee_in;
+        let swap_fee = get_swap_fee(&pool_info, pool_amount_in);
+        if pool_amount_in <= swap_fee {
+            return Err(eyre::eyre!("amount in does not cover the fees"));
+        }
+        let pool_amount_out = get_swap_amount_out(
+            &pool_info,
+            base_vault_amount,
+            quote_vault_amount,
+            pool_amount_in,
+            base_in,
+        );
+        let transfer_fee_out = mint_info_out.transfer_fee(pool_amount_out, epoch);
+        let expected_out = pool_amount_out - transfer_fee_out;
+        let minimum_out = (expected_out as u128
+            * 10_000_u64.saturating_sub(self.slippage_bps) as u128
+            / 10_000) as u64;
+
+        let (reserve_in, reserve_out) =
+            get_pool_reserves(&pool_info, base_vault_amount, quote_vault_amount, base_in);
+        let spot_price = reserve_out as f64 / reserve_in as f64;
+        let execution_price = pool_amount_out as f64 / (pool_amount_in - swap_fee) as f64;
+        let price_impact_pct = (1. - execution_price / spot_price) * 100.;
+
+        Ok(Quote {
+            amm_id: amm_id.to_string(),
+            input_token: input_token.to_string(),
+            output_token: output_token.to_string(),
+            amount_in: amount_in as f64 / scale_in,
+            transfer_fee_in: transfer_fee_in as f64 / scale_in,
+            transfer_fee_out: transfer_fee_out as f64 / scale_out,
+            swap_fee: swap_fee as f64 / scale_in,
+            expected_out: expected_out as f64 / scale_out,
+            minimum_out: minimum_out as f64 / scale_out,
+            slippage_bps: self.slippage_bps,
+            price_impact_pct,
+        })
+    }
+
+    /// AMM and market ids of the pool, from the AMM id or the mints
+    async fn resolve_pool(&self, client: &RpcClient) -> Result<(Pubkey, Pubkey), eyre::Error> {
+        if let Some(amm_id) = &self.amm_id {
+            let amm_id = Pubkey::from_str(amm_id).map_err(|_| eyre::eyre!("invalid AMM id"))?;
+            let pool_account = client.get_account(&amm_id).await?;
+            let pool_info = PoolInfo::deserialize(&mut &pool_account.data[..])?;
+            return Ok((amm_id, pool_info.market_id));
+        }
+
+        // safe to unwrap, clap requires the output token without an AMM id
+        let output_token_address = self.output_token_address.as_deref().unwrap();
+        let market_id = find_market_id(client, &self.input_token_address, output_token_address)
+            .await?
+            .ok_or_else(|| eyre::eyre!("no market for this pair"))?;
+        Ok((get_amm_id(&market_id), market_id))
+    }
+}
+
+fn get_mint_infos(
+    mints: &[Pubkey],
+    accounts: Vec<Option<solana_sdk::account::Account>>,
+) -> Result<Vec<MintInfo>, eyre::Error> {
+    mints
+        .iter()
+        .zip(accounts)
+        .map(|(mint, account)| {
+            let account = account.ok_or_else(|| eyre::eyre!("mint {} not found", mint))?;
+            MintInfo::from_account(*mint, &account)
+        })
+        .collect()
+}
+
+fn print_quote(quote: &Quote) {
+    println!("pool:             {}", quote.amm_id);
+    println!(
+        "amount in:        {} {}",
+        quote.amount_in, quote.input_token
+    );
+    println!(
+        "expected out:     {} {}",
+        quote.expected_out, quote.output_token
+    );
+    println!(
+        "minimum out:      {} ({} bps slippage)",
+        quote.minimum_out, quote.slippage_bps
+    );
+    println!("swap fee:         {}", quote.swap_fee);
+    if quote.transfer_fee_in > 0. || quote.transfer_fee_out > 0. {
+        println!(
+            "transfer fees:    {} in, {} out",
+            quote.transfer_fee_in, quote.transfer_fee_out
+        );
+    }
+    println!("price impact:     {:.4}%", quote.price_impact_pct);
+}
@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use clap::Args;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    constants::LAMPORTS_PER_SOL,
+    sender::sign_and_send_instructions,
+    signer::TransactionSigner,
+    types::WalletTokenAccount,
+    utils::{get_close_token_account_instructions, get_wallet_token_accounts},
+};
+
+/// Rent reclaimed from a wallet and the number of empty accounts that could
+/// not be closed, or why the wallet couldn't be processed
+type ReclaimOutcome = Result<(u64, usize), String>;
+
+#[derive(Debug, Args)]
+pub struct ReclaimRentSubcommand {
+    /// Token accounts closed in a single transaction
+    #[arg(short, long, default_value_t = 10)]
+    pub batch_size: usize,
+}
+
+impl ReclaimRentSubcommand {
+    pub async fn run(self, client: Arc<RpcClient>, wallets: Vec<Arc<dyn TransactionSigner>>) {
+        let mut outcomes = vec![];
+        for wallet in &wallets {
+            let outcome = self
+                .reclaim_rent(&client, wallet.as_ref())
+                .await
+                .map_err(|e| {
+                    tracing::error!("failed to reclaim rent of {}: {:?}", wallet.pubkey(), e);
+                    e.to_string()
+                });
+            outcomes.push((wallet.pubkey(), outcome));
+        }
+
+        print_summary(&outcomes);
+        let failed = outcomes
+            .iter()
+            .any(|(_, outcome)| !matches!(outcome, Ok((_, 0))));
+        if failed {
+            std::process::exit(1);
+        }
+    }
+
+    /// Closes the empty token accounts of the wallet, returning their rent to
+    /// it. A failed batch doesn't stop the others, the rent reclaimed and the
+    /// accounts left open are returned.
+    async fn reclaim_rent(
+        &self,
+        client: &RpcClient,
+        wallet: &dyn TransactionSigner,
+    ) -> Result<(u64, usize), eyre::Error> {
+        let owner = wallet.pubkey();
+        let empty_accounts: Vec<WalletTokenAccount> = get_wallet_token_accounts(client, &owner)
+            .await?
+            .into_iter()
+            .filter(|token_account| token_account.amount == 0)
+            .collect();
+
+        if empty_accounts.is_empty() {
+            tracing::info!("no empty token accounts for {}", owner);
+            return Ok((0, 0));
+        }
+
+        let balance_before = client.get_balance(&owner).await?;
+        let mut failed_accounts = 0;
+        for batch in empty_accounts.chunks(self.batch_size.max(1)) {
+            let instructions = batch
+                .iter()
+                .map(|token_account| get_close_token_account_instructions(&owner, token_account))
+                .collect::<Result<Vec<_>, _>>()?
+                .concat();
+            match sign_and_send_instructions(client, wallet, &instructions, &[]).await {
+                Ok(signature) => tracing::info!(
+                    "closed {} token accounts of {} (sig: {:?})",
+                    batch.len(),
+                    owner,
+                    signature
+                ),
+                Err(e) => {
+                    tracing::error!(
+                        "failed to close {} token accounts of {}: {:?}",
+                        batch.len(),
+                        owner,
+                        e
+                    );
+                    failed_accounts += batch.len();
+                }
+            }
+        }
+        let balance_after = client.get_balance(&owner).await?;
+        let reclaimed_lamports = balance_after.saturating_sub(balance_before);
+        tracing::info!(
+            "reclaimed {} SOL for {}",
+            reclaimed_lamports as f64 / *LAMPORTS_PER_SOL,
+            owner
+        );
+        Ok((reclaimed_lamports, failed_accounts))
+    }
+}
+
+fn print_summary(outcomes: &[(Pubkey, ReclaimOutcome)]) {
+    println!("{:<44} {:>14}  result", "wallet", "SOL");
+    let mut total_lamports = 0;
+    for (wallet, outcome) in outcomes {
+        match outcome {
+            Ok((lamports, failed_accounts)) => {
+                total_lamports += lamports;
+                let result = if *failed_accounts == 0 {
+                    "closed".to_string()
+                } else {
+                    format!("{} accounts not closed", failed_accounts)
+                };
+                println!(
+                    "{:<44} {:>14.9}  {}",
+                    wallet,
+                    *lamports as f64 / *LAMPORTS_PER_SOL,
+                    result
+                );
+            }
+            Err(error) => println!("{:<44} {:>14}  {}", wallet, "-", error),
+        }
+    }
+    println!(
+        "{:<44} {:>14.9}",
+        "total",
+        total_lamports as f64 / *LAMPORTS_PER_SOL
+    );
+}
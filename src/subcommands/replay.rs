@@ -0,0 +1,77 @@
+use std::path::PathBuf;
+
+use clap::Args;
+
+use crate::{
+    actors::swapper::strategy::Strategy,
+    constants::LAMPORTS_PER_SOL,
+    recording::replay::{replay_recordings, ReplayConfig, ReplayTrade},
+};
+
+#[derive(Debug, Args)]
+pub struct ReplaySubcommand {
+    /// Recordings written by `listen --record`, replayed one after the other
+    #[arg(required = true)]
+    pub recordings: Vec<PathBuf>,
+
+    /// Input max swappers
+    #[arg(short, long, default_value = "1")]
+    pub max_swappers: usize,
+
+    /// Input trade amount
+    #[arg(short, long, default_value = "0.001")]
+    pub trade_amount: f64,
+
+    /// Starting virtual SOL balance
+    #[arg(long, default_value_t = 10.)]
+    pub paper_sol: f64,
+
+    /// Replay speed as a multiple of the recorded pace, 0 replays as fast as possible
+    #[arg(long, default_value_t = 0.)]
+    pub speed: f64,
+}
+
+impl ReplaySubcommand {
+    pub async fn run(self) {
+        let trades = replay_recordings(
+            &self.recordings,
+            ReplayConfig {
+                strategy: Strategy::default(),
+                trade_amount: self.trade_amount,
+                max_swappers: self.max_swappers,
+                paper_sol: self.paper_sol,
+            },
+            self.speed,
+        )
+        .await
+        .expect("failed to replay");
+
+        print_trades(&trades);
+    }
+}
+
+fn print_trades(trades: &[ReplayTrade]) {
+    println!(
+        "{:<44} {:<44} {:>14} {:>10}  outcome",
+        "amm id", "mint", "PnL SOL", "return %"
+    );
+    for trade in trades {
+        println!(
+            "{:<44} {:<44} {:>14.9} {:>10.2}  {:?}",
+            trade.amm_id,
+            trade.mint.map(|mint| mint.to_string()).unwrap_or_default(),
+            trade.pnl_lamports() as f64 / *LAMPORTS_PER_SOL,
+            trade.return_pct(),
+            trade.outcome
+        );
+    }
+
+    let entered: Vec<&ReplayTrade> = trades.iter().filter(|trade| trade.is_entered()).collect();
+    let pnl_lamports: i64 = entered.iter().map(|trade| trade.pnl_lamports()).sum();
+    println!(
+        "{} pools replayed, {} entered, PnL {:.9} SOL",
+        trades.len(),
+        entered.len(),
+        pnl_lamports as f64 / *LAMPORTS_PER_SOL
+    );
+}
@@ -0,0 +1,145 @@
+use std::sync::Arc;
+
+use clap::Args;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    address_lookup_table::AddressLookupTableAccount, pubkey::Pubkey, signature::Signature,
+};
+use spl_associated_token_account::get_associated_token_address;
+
+use crate::{
+    actors::swapper::actor::Swapper,
+    constants::{LAMPORTS_PER_SOL, SOL},
+    journal::Journal,
+    sender::lookup_table::load_configured_lookup_tables,
+    signer::TransactionSigner,
+    types::{ProgramConfig, WalletTokenAccount},
+    utils::{find_market_id, get_wallet_token_accounts},
+};
+
+/// SOL received and signature of a sale, or why it failed
+type SaleOutcome = Result<(i64, Signature), String>;
+
+#[derive(Debug, Args)]
+pub struct SellAllSubcommand {
+    /// Maximum slippage below the pool quote, in basis points
+    #[arg(short, long, default_value_t = 2_500)]
+    pub slippage_bps: u64,
+}
+
+impl SellAllSubcommand {
+    pub async fn run(
+        self,
+        client: Arc<RpcClient>,
+        config: ProgramConfig,
+        signer: Arc<dyn TransactionSigner>,
+    ) {
+        let token_accounts = get_wallet_token_accounts(&client, &signer.pubkey())
+            .await
+            .expect("failed to get token accounts");
+
+        let journal = Journal::from_config(&config);
+        let lookup_tables = load_configured_lookup_tables(&client, &config)
+            .await
+            .expect("failed to load lookup table");
+        let mut proceeds = vec![];
+        for token_account in token_accounts
+            .into_iter()
+            .filter(|token_account| token_account.amount > 0 && token_account.mint != *SOL)
+        {
+            let outcome = self
+                .sell(
+                    &client,
+                    &config,
+                    &signer,
+                    journal.clone(),
+                    &lookup_tables,
+                    &token_account,
+                )
+                .await
+                .map_err(|e| {
+                    tracing::error!("failed to sell {}: {:?}", token_account.mint, e);
+                    e.to_string()
+                });
+            proceeds.push((token_account, outcome));
+        }
+
+        print_summary(&proceeds);
+        if proceeds.iter().any(|(_, outcome)| outcome.is_err()) {
+            std::process::exit(1);
+        }
+    }
+
+    /// Sells the whole balance of the token account through its Raydium pool,
+    /// returning the SOL received net of fees and rent changes
+    async fn sell(
+        &self,
+        client: &Arc<RpcClient>,
+        config: &ProgramConfig,
+        signer: &Arc<dyn TransactionSigner>,
+        journal: Option<Arc<Journal>>,
+        lookup_tables: &[AddressLookupTableAccount],
+        token_account: &WalletTokenAccount,
+    ) -> Result<(i64, Signature), eyre::Error> {
+        let market_id = find_market_id(client, &SOL.to_string(), &token_account.mint.to_string())
+            .await?
+            .ok_or_else(|| eyre::eyre!("no SOL market"))?;
+
+        let mut swapper = Swapper::new(
+            Arc::clone(client),
+            config.clone(),
+            Arc::clone(signer),
+            market_id,
+            0.,
+        )
+        .await?
+        .with_journal(journal)
+        .with_lookup_tables(lookup_tables.to_vec());
+
+        let lamports_before = get_sol_value(client, &signer.pubkey()).await?;
+        let (_, signature) = swapper.sell_all(self.slippage_bps).await?;
+        let lamports_after = get_sol_value(client, &signer.pubkey()).await?;
+        Ok((lamports_after as i64 - lamports_before as i64, signature))
+    }
+}
+
+/// Lamports held by the wallet and its WSOL account
+async fn get_sol_value(client: &RpcClient, owner: &Pubkey) -> Result<u64, eyre::Error> {
+    let accounts = client
+        .get_multiple_accounts(&[*owner, get_associated_token_address(owner, &SOL)])
+        .await?;
+    Ok(accounts
+        .iter()
+        .flatten()
+        .map(|account| account.lamports)
+        .sum())
+}
+
+fn print_summary(proceeds: &[(WalletTokenAccount, SaleOutcome)]) {
+    println!("{:<44} {:>18} {:>14}  result", "mint", "amount", "SOL");
+    let mut total_lamports = 0;
+    for (token_account, outcome) in proceeds {
+        match outcome {
+            Ok((lamports, signature)) => {
+                total_lamports += lamports;
+                println!(
+                    "{:<44} {:>18} {:>14.9}  {}",
+                    token_account.mint,
+                    token_account.ui_amount,
+                    *lamports as f64 / *LAMPORTS_PER_SOL,
+                    signature
+                );
+            }
+            Err(error) => println!(
+                "{:<44} {:>18} {:>14}  {}",
+                token_account.mint, token_account.ui_amount, "-", error
+            ),
+        }
+    }
+    println!(
+        "{:<44} {:>18} {:>14.9}",
+        "total",
+        "",
+        total_lamports as f64 / *LAMPORTS_PER_SOL
+    );
+}
@@ -0,0 +1,47 @@
+use std::{path::PathBuf, sync::Arc};
+
+use clap::Args;
+use solana_sdk::signature::Keypair;
+
+use crate::{
+    constants::{COMPUTE_UNIT_LIMIT, COMPUTE_UNIT_PRICE_MICRO_LAMPORTS, LAMPORTS_PER_SOL},
+    signer::{
+        policy::{LocalSigningService, SigningPolicy},
+        remote::serve_unix_socket,
+    },
+};
+
+#[derive(Debug, Args)]
+pub struct SigningServiceSubcommand {
+    /// Unix socket to serve sign requests on
+    #[arg(short, long)]
+    pub socket: PathBuf,
+    /// Maximum amount of SOL a single transaction can spend
+    #[arg(short, long)]
+    pub max_sol: f64,
+    /// Highest compute unit price a transaction can set, in micro-lamports
+    #[arg(long, default_value_t = COMPUTE_UNIT_PRICE_MICRO_LAMPORTS)]
+    pub max_cu_price: u64,
+    /// Highest compute unit limit a transaction can set
+    #[arg(long, default_value_t = COMPUTE_UNIT_LIMIT)]
+    pub max_cu_limit: u32,
+}
+
+impl SigningServiceSubcommand {
+    /// Holds the keypair and signs the bot's transactions that pass the
+    /// signing policy, for use with `REMOTE_SIGNER_URL=unix:<socket>`
+    pub async fn run(self, keypair: Keypair) {
+        let signer = LocalSigningService::new(
+            keypair,
+            SigningPolicy {
+                max_lamports_in: (self.max_sol * *LAMPORTS_PER_SOL) as u64,
+                max_cu_price: self.max_cu_price,
+                max_cu_limit: self.max_cu_limit,
+            },
+        );
+        tracing::info!("serving sign requests on {:?}", self.socket);
+        serve_unix_socket(&self.socket, Arc::new(signer))
+            .await
+            .expect("signing service stopped");
+    }
+}
@@ -0,0 +1,265 @@
+use std::{str::FromStr, sync::Arc};
+
+use clap::{Args, Subcommand};
+use eyre::eyre;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, system_instruction};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id,
+    instruction::create_associated_token_account_idempotent,
+};
+
+use crate::{
+    constants::{LAMPORTS_PER_SIGNATURE, LAMPORTS_PER_SOL},
+    sender::sign_and_send_instructions,
+    signer::TransactionSigner,
+    utils::{get_close_token_account_instructions, get_wallet_token_accounts},
+};
+
+/// Transfers batched in a single top up transaction
+const TRANSFERS_PER_TRANSACTION: usize = 20;
+/// Token accounts swept in a single transaction, each takes three instructions
+const TOKEN_ACCOUNTS_PER_TRANSACTION: usize = 5;
+
+#[derive(Debug, Args)]
+pub struct WalletsSubcommand {
+    #[clap(subcommand)]
+    pub action: WalletsAction,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum WalletsAction {
+    /// List SOL balances and token holdings of the trading wallets
+    List,
+    /// Top up the trading wallets to a target SOL balance from the buyer wallet
+    TopUp {
+        /// SOL balance every trading wallet should hold
+        #[arg(short, long)]
+        target_sol: f64,
+    },
+    /// Send every token and the remaining SOL of the trading wallets to a treasury
+    Sweep {
+        /// Treasury address receiving the funds
+        #[arg(short, long)]
+        treasury: String,
+    },
+}
+
+impl WalletsSubcommand {
+    pub async fn run(
+        self,
+        client: Arc<RpcClient>,
+        funding_wallet: Arc<dyn TransactionSigner>,
+        wallets: Vec<Arc<dyn TransactionSigner>>,
+    ) {
+        match self.action {
+            WalletsAction::List => {
+                if let Err(e) = list(&client, &wallets).await {
+                    tracing::error!("failed to list wallets: {:?}", e);
+                    std::process::exit(1);
+                }
+            }
+            WalletsAction::TopUp { target_sol } => {
+                if let Err(e) = top_up(&client, funding_wallet.as_ref(), &wallets, target_sol).await
+                {
+                    tracing::error!("failed to top up wallets: {:?}", e);
+                    std::process::exit(1);
+                }
+            }
+            WalletsAction::Sweep { treasury } => {
+                let treasury = Pubkey::from_str(&treasury).expect("Enter correct treasury address");
+                let mut outcomes = vec![];
+                for wallet in &wallets {
+                    let outcome = sweep(&client, wallet.as_ref(), &treasury)
+                        .await
+                        .map_err(|e| {
+                            tracing::error!("failed to sweep wallet {}: {:?}", wallet.pubkey(), e);
+                            e.to_string()
+                        });
+                    outcomes.push((wallet.pubkey(), outcome));
+                }
+
+                print_sweep_summary(&outcomes);
+                if outcomes.iter().any(|(_, outcome)| outcome.is_err()) {
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+}
+
+async fn list(
+    client: &RpcClient,
+    wallets: &[Arc<dyn TransactionSigner>],
+) -> Result<(), eyre::Error> {
+    let mut total_lamports = 0;
+    for wallet in wallets {
+        let pubkey = wallet.pubkey();
+        let lamports = client.get_balance(&pubkey).await?;
+        total_lamports += lamports;
+        println!(
+            "{:<44} {:>14.9} SOL",
+            pubkey,
+            lamports as f64 / *LAMPORTS_PER_SOL
+        );
+
+        let token_accounts = get_wallet_token_accounts(client, &pubkey).await?;
+        for token_account in token_accounts {
+            println!(
+                "    {:<44} {:>18}",
+                token_account.mint, token_account.ui_amount
+            );
+        }
+    }
+    println!(
+        "{:<44} {:>14.9} SOL",
+        "total",
+        total_lamports as f64 / *LAMPORTS_PER_SOL
+    );
+    Ok(())
+}
+
+async fn top_up(
+    client: &RpcClient,
+    funding_wallet: &dyn TransactionSigner,
+    wallets: &[Arc<dyn TransactionSigner>],
+    target_sol: f64,
+) -> Result<(), eyre::Error> {
+    let target_lamports = (target_sol * *LAMPORTS_PER_SOL) as u64;
+    let pubkeys: Vec<Pubkey> = wallets.iter().map(|wallet| wallet.pubkey()).collect();
+    let accounts = client.get_multiple_accounts(&pubkeys).await?;
+
+    let transfers: Vec<Instruction> = pubkeys
+        .iter()
+        .zip(accounts)
+        .filter(|(pubkey, _)| **pubkey != funding_wallet.pubkey())
+        .filter_map(|(pubkey, account)| {
+            let lamports = account.map(|account| account.lamports).unwrap_or_default();
+            let missing_lamports = target_lamports.checked_sub(lamports)?;
+            (missing_lamports > 0).then(|| {
+                tracing::info!("topping up {} with {} lamports", pubkey, missing_lamports);
+                system_instruction::transfer(&funding_wallet.pubkey(), pubkey, missing_lamports)
+            })
+        })
+        .collect();
+
+    let mut failed_transfers = 0;
+    for batch in transfers.chunks(TRANSFERS_PER_TRANSACTION) {
+        match sign_and_send_instructions(client, funding_wallet, batch, &[]).await {
+            Ok(signature) => tracing::info!("topped up wallets (sig: {:?})", signature),
+            Err(e) => {
+                tracing::error!("failed to top up {} wallets: {:?}", batch.len(), e);
+                failed_transfers += batch.len();
+            }
+        }
+    }
+    if failed_transfers > 0 {
+        return Err(eyre!(
+            "{} of {} wallets not topped up",
+            failed_transfers,
+            transfers.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Moves every token to the treasury's associated token accounts and closes
+/// the emptied accounts, then sends the remaining SOL. A failed batch doesn't
+/// stop the others, but the SOL is kept to pay for a retry.
+async fn sweep(
+    client: &RpcClient,
+    wallet: &dyn TransactionSigner,
+    treasury: &Pubkey,
+) -> Result<(), eyre::Error> {
+    let owner = wallet.pubkey();
+    let token_accounts = get_wallet_token_accounts(client, &owner).await?;
+
+    let mut failed_accounts = 0;
+    for batch in token_accounts.chunks(TOKEN_ACCOUNTS_PER_TRANSACTION) {
+        let mut instructions = vec![];
+        for token_account in batch {
+            if token_account.amount > 0 {
+                let treasury_token_account = get_associated_token_address_with_program_id(
+                    treasury,
+                    &token_account.mint,
+                    &token_account.token_program,
+                );
+                instructions.push(create_associated_token_account_idempotent(
+                    &owner,
+                    treasury,
+                    &token_account.mint,
+                    &token_account.token_program,
+                ));
+                instructions.push(spl_token_2022::instruction::transfer_checked(
+                    &token_account.token_program,
+                    &token_account.address,
+                    &token_account.mint,
+                    &treasury_token_account,
+                    &owner,
+                    &[],
+                    token_account.amount,
+                    token_account.decimals,
+                )?);
+            }
+            instructions.extend(get_close_token_account_instructions(&owner, token_account)?);
+        }
+        match sign_and_send_instructions(client, wallet, &instructions, &[]).await {
+            Ok(signature) => tracing::info!(
+                "swept {} token accounts of {} (sig: {:?})",
+                batch.len(),
+                owner,
+                signature
+            ),
+            Err(e) => {
+                tracing::error!(
+                    "failed to sweep {} token accounts of {}: {:?}",
+                    batch.len(),
+                    owner,
+                    e
+                );
+                failed_accounts += batch.len();
+            }
+        }
+    }
+    if failed_accounts > 0 {
+        return Err(eyre!(
+            "{} of {} token accounts not swept",
+            failed_accounts,
+            token_accounts.len()
+        ));
+    }
+
+    let lamports = client.get_balance(&owner).await?;
+    if lamports <= LAMPORTS_PER_SIGNATURE {
+        return Ok(());
+    }
+    let instruction =
+        system_instruction::transfer(&owner, treasury, lamports - LAMPORTS_PER_SIGNATURE);
+    let signature = sign_and_send_instructions(client, wallet, &[instruction], &[]).await?;
+    tracing::info!(
+        "swept {} lamports of {} (sig: {:?})",
+        lamports,
+        owner,
+        signature
+    );
+    Ok(())
+}
+
+fn print_sweep_summary(outcomes: &[(Pubkey, Result<(), String>)]) {
+    println!("{:<44}  result", "wallet");
+    for (wallet, outcome) in outcomes {
+        match outcome {
+            Ok(()) => println!("{:<44}  swept", wallet),
+            Err(error) => println!("{:<44}  {}", wallet, error),
+        }
+    }
+    let failed = outcomes
+        .iter()
+        .filter(|(_, outcome)| outcome.is_err())
+        .count();
+    println!(
+        "{} of {} wallets swept",
+        outcomes.len() - failed,
+        outcomes.len()
+    );
+}
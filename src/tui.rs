@@ -0,0 +1,432 @@
+use std::{
+    io::{self, Stdout},
+    str::FromStr,
+    time::Duration,
+};
+
+use chrono::DateTime;
+use coerce::actor::LocalActorRef;
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState},
+    Frame, Terminal,
+};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    actors::{
+        listener::actor::{
+            Blacklist, ForceSell, GetStatus, Listener, ListenerStatus, SetPaused, Shutdown,
+            ShutdownPolicy,
+        },
+        swapper::control::{SwapperState, TransactionStatus},
+    },
+    utils::now_ms,
+};
+
+/// How often the dashboard is redrawn when no key is pressed
+const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Renders the listener status until the listener stops.
+///
+/// Hotkeys: up/down select a swapper, `s` force-sells its position, `b`
+/// blacklists its mint, `p` pauses or resumes new entries and `q` shuts the
+/// listener down with `shutdown_policy`, then exits right away when pressed
+/// again.
+pub async fn run(
+    listener: LocalActorRef<Listener>,
+    shutdown_policy: ShutdownPolicy,
+) -> Result<(), eyre::Error> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let result = Dashboard::new(listener, shutdown_policy)
+        .run(&mut terminal)
+        .await;
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    result
+}
+
+struct Dashboard {
+    listener: LocalActorRef<Listener>,
+    /// Applied on the first quit, the next one persists
+    shutdown_policy: ShutdownPolicy,
+    swappers: TableState,
+    /// Outcome of the last hotkey
+    message: Option<String>,
+}
+
+impl Dashboard {
+    fn new(listener: LocalActorRef<Listener>, shutdown_policy: ShutdownPolicy) -> Self {
+        Self {
+            listener,
+            shutdown_policy,
+            swappers: TableState::default(),
+            message: None,
+        }
+    }
+
+    async fn run(
+        &mut self,
+        terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    ) -> Result<(), eyre::Error> {
+        loop {
+            // The listener is gone once it stopped, or left running on exit
+            let Ok(status) = self.listener.send(GetStatus).await else {
+                return Ok(());
+            };
+            if status.shutdown_policy == Some(ShutdownPolicy::Persist) {
+                return Ok(());
+            }
+            self.clamp_selection(&status);
+            terminal.draw(|frame| self.draw(frame, &status))?;
+
+            let event = tokio::task::spawn_blocking(|| -> io::Result<Option<Event>> {
+                match event::poll(REFRESH_INTERVAL)? {
+                    true => event::read().map(Some),
+                    false => Ok(None),
+                }
+            })
+            .await??;
+            if let Some(Event::Key(key)) = event {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Up => self.select_previous(),
+                    KeyCode::Down => self.select_next(&status),
+                    KeyCode::Char('s') => self.force_sell(&status).await,
+                    KeyCode::Char('b') => self.blacklist(&status).await,
+                    KeyCode::Char('p') => self.toggle_paused(&status).await,
+                    KeyCode::Char('q') => self.shutdown().await,
+                    // Raw mode swallows the interrupt signal
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        self.shutdown().await
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn clamp_selection(&mut self, status: &ListenerStatus) {
+        let selected = match (self.swappers.selected(), status.swappers.len()) {
+            (_, 0) => None,
+            (None, _) => Some(0),
+            (Some(selected), len) => Some(selected.min(len - 1)),
+        };
+        self.swappers.select(selected);
+    }
+
+    fn select_previous(&mut self) {
+        if let Some(selected) = self.swappers.selected() {
+            self.swappers.select(Some(selected.saturating_sub(1)));
+        }
+    }
+
+    fn select_next(&mut self, status: &ListenerStatus) {
+        if let Some(selected) = self.swappers.selected() {
+            let last = status.swappers.len().saturating_sub(1);
+            self.swappers.select(Some((selected + 1).min(last)));
+        }
+    }
+
+    async fn force_sell(&mut self, status: &ListenerStatus) {
+        let Some(swapper) = self
+            .swappers
+            .selected()
+            .and_then(|i| status.swappers.get(i))
+        else {
+            return;
+        };
+        let Ok(amm_id) = Pubkey::from_str(&swapper.amm_id) else {
+            return;
+        };
+        self.message = Some(match self.listener.send(ForceSell(amm_id)).await {
+            Ok(Ok(())) => format!("selling {}", swapper.amm_id),
+            Ok(Err(e)) => e.to_string(),
+            Err(e) => format!("listener unavailable: {}", e),
+        });
+    }
+
+    async fn blacklist(&mut self, status: &ListenerStatus) {
+        let Some(swapper) = self
+            .swappers
+            .selected()
+            .and_then(|i| status.swappers.get(i))
+        else {
+            return;
+        };
+        let Ok(mint) = Pubkey::from_str(&swapper.mint) else {
+            return;
+        };
+        self.message = Some(match self.listener.send(Blacklist(mint)).await {
+            Ok(()) => format!("blacklisted {}", swapper.mint),
+            Err(e) => format!("listener unavailable: {}", e),
+        });
+    }
+
+    async fn toggle_paused(&mut self, status: &ListenerStatus) {
+        let paused = !status.paused;
+        self.message = Some(match self.listener.send(SetPaused(paused)).await {
+            Ok(()) if paused => "entries paused".to_string(),
+            Ok(()) => "entries resumed".to_string(),
+            Err(e) => format!("listener unavailable: {}", e),
+        });
+    }
+
+    async fn shutdown(&mut self) {
+        let policy = self.shutdown_policy;
+        self.shutdown_policy = ShutdownPolicy::Persist;
+        self.message = Some(match self.listener.send(Shutdown(policy)).await {
+            Ok(()) if policy == ShutdownPolicy::Persist => "exiting".to_string(),
+            Ok(()) => "shutting down, quit again to exit now".to_string(),
+            Err(e) => format!("listener unavailable: {}", e),
+        });
+    }
+
+    fn draw(&mut self, frame: &mut Frame, status: &ListenerStatus) {
+        let [header, swappers, activity, footer] = split(
+            Direction::Vertical,
+            frame.size(),
+            [
+                Constraint::Length(4),
+                Constraint::Min(6),
+                Constraint::Percentage(40),
+                Constraint::Length(1),
+            ],
+        );
+        let [feed, transactions] = split(
+            Direction::Horizontal,
+            activity,
+            [Constraint::Percentage(50), Constraint::Percentage(50)],
+        );
+
+        frame.render_widget(header_widget(status), header);
+        frame.render_stateful_widget(swappers_widget(status), swappers, &mut self.swappers);
+        frame.render_widget(feed_widget(status), feed);
+        frame.render_widget(transactions_widget(status), transactions);
+        let keys = "↑/↓ select  s sell  b blacklist mint  p pause/resume  q quit";
+        let footer_text = match &self.message {
+            Some(message) => format!("{}  |  {}", keys, message),
+            None => keys.to_string(),
+        };
+        frame.render_widget(Paragraph::new(footer_text), footer);
+    }
+}
+
+fn split<const N: usize>(
+    direction: Direction,
+    area: Rect,
+    constraints: [Constraint; N],
+) -> [Rect; N] {
+    let chunks = Layout::default()
+        .direction(direction)
+        .constraints(constraints)
+        .split(area);
+    std::array::from_fn(|i| chunks[i])
+}
+
+fn header_widget(status: &ListenerStatus) -> Paragraph<'static> {
+    let entries = match (status.shutdown_policy, status.paused) {
+        (Some(ShutdownPolicy::Drain), _) => Span::styled(
+            "shutting down, waiting for exits",
+            Style::default().fg(Color::Red),
+        ),
+        (Some(ShutdownPolicy::Liquidate), _) => Span::styled(
+            "shutting down, selling everything",
+            Style::default().fg(Color::Red),
+        ),
+        (Some(ShutdownPolicy::Persist), _) => {
+            Span::styled("exiting", Style::default().fg(Color::Red))
+        }
+        (None, true) => Span::styled("paused", Style::default().fg(Color::Yellow)),
+        (None, false) => Span::styled("listening", Style::default().fg(Color::Green)),
+    };
+    let health = &status.health;
+    let websocket = match health.websocket_connected {
+        true => Span::styled("connected", Style::default().fg(Color::Green)),
+        false => Span::styled("disconnected", Style::default().fg(Color::Red)),
+    };
+    let last_log = health
+        .last_log_at_ms
+        .map(|at_ms| format!("{}s ago", now_ms().saturating_sub(at_ms) / 1000))
+        .unwrap_or_else(|| "never".to_string());
+
+    Paragraph::new(vec![
+        Line::from(vec![
+            entries,
+            Span::raw(format!(
+                "  {}/{} swappers  {} SOL per trade  {} blacklisted mints",
+                status.swappers.len(),
+                status.max_swappers,
+                status.trade_amount,
+                status.blacklist.len()
+            )),
+        ]),
+        Line::from(vec![
+            Span::raw("websocket "),
+            websocket,
+            Span::raw(format!(
+                ", last log {}, {} reconnects  rpc errors {}",
+                last_log, health.websocket_reconnects, health.rpc_errors
+            )),
+        ]),
+    ])
+    .block(Block::default().borders(Borders::ALL).title("listener"))
+}
+
+fn swappers_widget(status: &ListenerStatus) -> Table<'static> {
+    let now_ms = now_ms();
+    let rows = status.swappers.iter().map(|swapper| {
+        let state = match (swapper.state, swapper.sell_requested) {
+            (SwapperState::Exited, _) => "exited",
+            (_, true) => "selling",
+            (SwapperState::Entering, false) => "entering",
+            (SwapperState::Holding, false) => "holding",
+        };
+        let pnl = swapper.unrealized_pnl_sol;
+        let pnl_style = match pnl {
+            Some(pnl) if pnl < 0. => Style::default().fg(Color::Red),
+            Some(_) => Style::default().fg(Color::Green),
+            None => Style::default(),
+        };
+        Row::new(vec![
+            Cell::from(short(&swapper.amm_id)),
+            Cell::from(short(&swapper.mint)),
+            Cell::from(state),
+            Cell::from(optional(swapper.entry_price, |price| {
+                format!("{:.4e}", price)
+            })),
+            Cell::from(optional(swapper.current_price, |price| {
+                format!("{:.4e}", price)
+            })),
+            Cell::from(optional(pnl, |pnl| format!("{:+.6}", pnl))).style(pnl_style),
+            Cell::from(optional(swapper.opened_at_ms, |opened_at_ms| {
+                held(now_ms.saturating_sub(opened_at_ms))
+            })),
+            Cell::from(swapper.price_checks.to_string()),
+        ])
+    });
+
+    Table::new(
+        rows,
+        [
+            Constraint::Length(12),
+            Constraint::Length(12),
+            Constraint::Length(9),
+            Constraint::Length(12),
+            Constraint::Length(12),
+            Constraint::Length(11),
+            Constraint::Length(9),
+            Constraint::Length(7),
+        ],
+    )
+    .header(
+        Row::new(vec![
+            "amm id", "mint", "state", "entry", "price", "PnL SOL", "held", "checks",
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+    .block(Block::default().borders(Borders::ALL).title("swappers"))
+}
+
+fn feed_widget(status: &ListenerStatus) -> Table<'static> {
+    let rows = status.feed.iter().map(|pool| {
+        let style = match pool.verdict {
+            "spawned" | "bought" => Style::default().fg(Color::Green),
+            "failed" => Style::default().fg(Color::Red),
+            _ => Style::default().fg(Color::DarkGray),
+        };
+        Row::new(vec![
+            Cell::from(time(pool.at_ms)),
+            Cell::from(short(&pool.amm_id)),
+            Cell::from(pool.verdict).style(style),
+        ])
+    });
+
+    Table::new(
+        rows,
+        [
+            Constraint::Length(8),
+            Constraint::Length(12),
+            Constraint::Min(12),
+        ],
+    )
+    .header(
+        Row::new(vec!["time", "amm id", "verdict"])
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(Block::default().borders(Borders::ALL).title("pools"))
+}
+
+fn transactions_widget(status: &ListenerStatus) -> Table<'static> {
+    let rows = status.transactions.iter().map(|transaction| {
+        let (status, style) = match transaction.status {
+            TransactionStatus::Pending => ("pending", Style::default().fg(Color::Yellow)),
+            TransactionStatus::Landed => ("landed", Style::default().fg(Color::Green)),
+            TransactionStatus::Failed => ("failed", Style::default().fg(Color::Red)),
+        };
+        Row::new(vec![
+            Cell::from(time(transaction.at_ms)),
+            Cell::from(transaction.side.as_str()),
+            Cell::from(short(&transaction.amm_id)),
+            Cell::from(status).style(style),
+            Cell::from(short(&transaction.signature)),
+        ])
+    });
+
+    Table::new(
+        rows,
+        [
+            Constraint::Length(8),
+            Constraint::Length(4),
+            Constraint::Length(12),
+            Constraint::Length(7),
+            Constraint::Min(12),
+        ],
+    )
+    .header(
+        Row::new(vec!["time", "side", "amm id", "status", "signature"])
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(Block::default().borders(Borders::ALL).title("transactions"))
+}
+
+/// First and last characters of an address
+fn short(address: &str) -> String {
+    match address.len() {
+        len if len > 12 => format!("{}..{}", &address[..5], &address[len - 5..]),
+        _ => address.to_string(),
+    }
+}
+
+fn optional<T>(value: Option<T>, format: impl FnOnce(T) -> String) -> String {
+    value.map(format).unwrap_or_else(|| "-".to_string())
+}
+
+/// UTC time of day
+fn time(at_ms: u64) -> String {
+    DateTime::from_timestamp_millis(at_ms as i64)
+        .map(|at| at.format("%H:%M:%S").to_string())
+        .unwrap_or_default()
+}
+
+fn held(held_ms: u64) -> String {
+    let secs = held_ms / 1000;
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
@@ -1,8 +1,16 @@
 use borsh::BorshDeserialize;
+use eyre::eyre;
 use serde::Deserialize;
-use solana_sdk::pubkey::Pubkey;
+use solana_sdk::{account::Account, program_pack::Pack, pubkey::Pubkey};
+use spl_token_2022::{
+    extension::{transfer_fee::TransferFeeConfig, BaseStateWithExtensions, StateWithExtensions},
+    state::{AccountState, Mint},
+};
 
-#[derive(Debug, PartialEq, BorshDeserialize)]
+use crate::constants::{TOKEN_2022_PROGRAM, TOKEN_PROGRAM};
+
+/// Token account fields, for both SPL Token and Token-2022 accounts
+#[derive(Debug, PartialEq)]
 pub struct TokenAccount {
     pub mint: Pubkey,
     pub owner: Pubkey,
@@ -14,13 +22,33 @@ pub struct TokenAccount {
     pub close_authority: Option<Pubkey>,
 }
 
-#[repr(u8)]
-#[derive(Debug, PartialEq, BorshDeserialize)]
-#[borsh(use_discriminant = false)]
-pub enum AccountState {
-    Uninitialized = 0,
-    Initialized = 1,
-    Frozen = 2,
+impl TokenAccount {
+    /// Unpacks the account with the layout of the token program owning it,
+    /// Token-2022 accounts may carry extensions after the base state
+    pub fn from_account(address: &Pubkey, account: &Account) -> Result<Self, eyre::Error> {
+        let state = if account.owner == *TOKEN_2022_PROGRAM {
+            StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data)?.base
+        } else if account.owner == *TOKEN_PROGRAM {
+            // Same layout as the Token-2022 base state
+            spl_token_2022::state::Account::unpack(&account.data)?
+        } else {
+            return Err(eyre!(
+                "token account {} is not owned by a token program",
+                address
+            ));
+        };
+
+        Ok(Self {
+            mint: state.mint,
+            owner: state.owner,
+            amount: state.amount,
+            delegate: state.delegate.into(),
+            state: state.state,
+            is_native: state.is_native.into(),
+            delegated_amount: state.delegated_amount,
+            close_authority: state.close_authority.into(),
+        })
+    }
 }
 
 #[derive(Debug, Default, PartialEq, BorshDeserialize)]
@@ -108,11 +136,77 @@ pub struct MarketInfo {
     pub blob_1: [u8; 7],
 }
 
+/// Mint fields needed to trade a token, for both SPL Token and Token-2022 mints
+#[derive(Debug, Clone)]
+pub struct MintInfo {
+    pub mint: Pubkey,
+    /// Token program owning the mint, used to derive and create its ATAs
+    pub token_program: Pubkey,
+    /// Token-2022 transfer fee extension, if the mint has one
+    pub transfer_fee_config: Option<TransferFeeConfig>,
+}
+
+impl MintInfo {
+    pub fn from_account(mint: Pubkey, account: &Account) -> Result<Self, eyre::Error> {
+        if account.owner != *TOKEN_PROGRAM && account.owner != *TOKEN_2022_PROGRAM {
+            return Err(eyre!("mint {} is not owned by a token program", mint));
+        }
+
+        let state = StateWithExtensions::<Mint>::unpack(&account.data)?;
+        let transfer_fee_config = state.get_extension::<TransferFeeConfig>().ok().copied();
+
+        Ok(Self {
+            mint,
+            token_program: account.owner,
+            transfer_fee_config,
+        })
+    }
+
+    /// Fee withheld by Token-2022 when transferring `amount` during `epoch`
+    pub fn transfer_fee(&self, amount: u64, epoch: u64) -> u64 {
+        self.transfer_fee_config
+            .as_ref()
+            .and_then(|config| config.calculate_epoch_fee(epoch, amount))
+            .unwrap_or(0)
+    }
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct ProgramConfig {
     pub ws_rpc_url: String,
     pub http_rpc_url: String,
-    pub buyer_private_key: String,
+    /// Base58 encoded private key, prefer a keypair file or keystore
+    pub buyer_private_key: Option<String>,
+    /// Solana CLI JSON keypair file
+    pub buyer_keypair_path: Option<String>,
+    /// Passphrase encrypted keypair, see `keystore` subcommand
+    pub buyer_keystore_path: Option<String>,
+    /// Signing service url, `http(s)://...` or `unix:/path/to/socket`
+    pub remote_signer_url: Option<String>,
+    /// Public key of the wallet held by the signing service
+    pub remote_signer_pubkey: Option<String>,
+    /// Apply the signing service policy to local keypairs, with this cap
+    pub signing_policy_max_sol: Option<f64>,
+    /// Highest compute unit price the signing policy allows, in micro-lamports
+    pub signing_policy_max_cu_price: Option<u64>,
+    /// Highest compute unit limit the signing policy allows
+    pub signing_policy_max_cu_limit: Option<u32>,
+    /// Directory of JSON keypair files, one wallet per concurrent swapper
+    pub wallets_dir: Option<String>,
+    /// Lookup table holding the static swap accounts, see `lookup-table` subcommand
+    pub lookup_table_address: Option<String>,
+    /// Nonce account sells are built against, see `nonce` subcommand
+    pub nonce_account_address: Option<String>,
+    /// Close the WSOL account after sells so proceeds come back as native SOL
+    #[serde(default)]
+    pub unwrap_sol_after_sell: bool,
+    /// Close the target token account after selling the whole balance
+    #[serde(default)]
+    pub close_token_account_after_sell: bool,
+    /// SQLite database positions are persisted to, resumed by `listen` on startup
+    pub positions_db_path: Option<String>,
+    /// SQLite database swaps are journaled to, see `journal` subcommand
+    pub journal_db_path: Option<String>,
 }
 
 impl ProgramConfig {
@@ -125,9 +219,25 @@ impl ProgramConfig {
     }
 }
 
+/// Token account held by one of our wallets, as returned by
+/// `getTokenAccountsByOwner`
+#[derive(Debug, Clone)]
+pub struct WalletTokenAccount {
+    pub address: Pubkey,
+    pub mint: Pubkey,
+    pub token_program: Pubkey,
+    pub amount: u64,
+    pub decimals: u8,
+    pub ui_amount: String,
+    /// Token-2022 transfer fees withheld in the account, which must be
+    /// harvested to the mint before it can be closed
+    pub withheld_amount: u64,
+}
+
 #[derive(Debug, Clone)]
 pub struct UserTokenAccounts {
     pub user_base_token_account: Pubkey,
     pub user_quote_token_account: Pubkey,
-    pub account_to_create: Option<Pubkey>,
+    /// Mints for which the user's associated token account is missing
+    pub accounts_to_create: Vec<Pubkey>,
 }
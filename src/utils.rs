@@ -1,47 +1,158 @@
+use std::{
+    path::Path,
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
 use borsh::BorshDeserialize;
+use clap::ValueEnum;
 use eyre::Result;
 use eyre::{eyre, OptionExt};
-use solana_account_decoder::UiAccountEncoding;
+use lazy_static::lazy_static;
+use solana_account_decoder::{parse_token::UiTokenAmount, UiAccountData, UiAccountEncoding};
 use solana_client::{
     nonblocking::rpc_client::RpcClient,
     rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcTransactionConfig},
     rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType},
+    rpc_request::TokenAccountsFilter,
 };
 use solana_sdk::account::{Account, ReadableAccount};
 use solana_sdk::{
-    commitment_config::CommitmentConfig,
-    compute_budget::ComputeBudgetInstruction,
-    instruction::Instruction,
-    pubkey::Pubkey,
-    signature::{Keypair, Signature},
-    signer::Signer,
+    commitment_config::CommitmentConfig, compute_budget::ComputeBudgetInstruction,
+    instruction::Instruction, pubkey::Pubkey, signature::Signature, system_instruction,
 };
 use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
-use spl_associated_token_account::get_associated_token_address;
-use tracing_subscriber::{filter, FmtSubscriber};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id,
+    instruction::create_associated_token_account_idempotent,
+};
+use spl_token_2022::extension::transfer_fee::instruction::harvest_withheld_tokens_to_mint;
+use tracing_appender::rolling;
+use tracing_subscriber::{
+    filter, fmt::MakeWriter, layer::SubscriberExt, util::SubscriberInitExt, Layer, Registry,
+};
 
 use crate::actors::swapper::actor::PoolInitTxInfos;
-use crate::types::{TokenAccount, UserTokenAccounts};
+use crate::types::{MintInfo, TokenAccount, UserTokenAccounts, WalletTokenAccount};
 use crate::{
-    constants::OPENBOOK,
+    constants::{
+        AMM_V4, COMPUTE_UNIT_LIMIT, COMPUTE_UNIT_PRICE_MICRO_LAMPORTS, OPENBOOK, SOL,
+        TOKEN_2022_PROGRAM, TOKEN_PROGRAM,
+    },
+    metrics::rpc_error,
     types::{MarketInfo, PoolInfo},
 };
 
-pub fn init_logging() {
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// One JSON object per line, with the fields of the enclosing spans
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum LogRotation {
+    Minutely,
+    Hourly,
+    #[default]
+    Daily,
+    Never,
+}
+
+/// Logs to stderr, and to files rotated in `log_dir` when given. File logs
+/// are written synchronously so none are lost when a subcommand exits.
+pub fn init_logging(
+    format: LogFormat,
+    console: bool,
+    log_dir: Option<&Path>,
+    rotation: LogRotation,
+) {
     let filter = if let Ok(filter) = std::env::var("RUST_LOG") {
         filter
     } else {
         "solana_buyer=info".to_string()
     };
     let filter = filter::EnvFilter::new(filter);
-    let subscriber = FmtSubscriber::builder().with_env_filter(filter).finish();
-    tracing::subscriber::set_global_default(subscriber).expect("setting tracing default failed");
+
+    // Logs go to stderr, stdout is kept for subcommand output
+    let mut layers = vec![];
+    if console {
+        layers.push(fmt_layer(format, std::io::stderr, true));
+    }
+    if let Some(log_dir) = log_dir {
+        let appender = match rotation {
+            LogRotation::Minutely => rolling::minutely(log_dir, "solana-buyer.log"),
+            LogRotation::Hourly => rolling::hourly(log_dir, "solana-buyer.log"),
+            LogRotation::Daily => rolling::daily(log_dir, "solana-buyer.log"),
+            LogRotation::Never => rolling::never(log_dir, "solana-buyer.log"),
+        };
+        layers.push(fmt_layer(format, appender, false));
+    }
+
+    Registry::default()
+        .with(layers)
+        .with(filter)
+        .try_init()
+        .expect("setting tracing default failed");
+}
+
+fn fmt_layer<W>(format: LogFormat, writer: W, ansi: bool) -> Box<dyn Layer<Registry> + Send + Sync>
+where
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(ansi);
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .boxed(),
+    }
+}
+
+/// Target slot time, slots don't come in faster
+const SLOT_DURATION: Duration = Duration::from_millis(400);
+
+lazy_static! {
+    /// Current epoch and the earliest time it can end at
+    static ref CACHED_EPOCH: Mutex<Option<(u64, Instant)>> = Mutex::new(None);
+}
+
+/// Current epoch, fetched again only once it may have ended
+pub async fn get_epoch(client: &RpcClient) -> Result<u64> {
+    if let Some((epoch, ends_at)) = *CACHED_EPOCH.lock().unwrap() {
+        if Instant::now() < ends_at {
+            return Ok(epoch);
+        }
+    }
+
+    let epoch_info = client.get_epoch_info().await?;
+    let remaining_slots = epoch_info
+        .slots_in_epoch
+        .saturating_sub(epoch_info.slot_index);
+    let ends_at = Instant::now() + SLOT_DURATION * remaining_slots as u32;
+    *CACHED_EPOCH.lock().unwrap() = Some((epoch_info.epoch, ends_at));
+    Ok(epoch_info.epoch)
+}
+
+/// Milliseconds since the unix epoch
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
 }
 
 pub fn get_prio_fee_instructions() -> (Instruction, Instruction) {
-    let prio_fee = 130_000;
+    let prio_fee = COMPUTE_UNIT_PRICE_MICRO_LAMPORTS;
     tracing::debug!("priority fee {:?}", prio_fee);
-    let compute_unit_limit_instruction = ComputeBudgetInstruction::set_compute_unit_limit(70_000);
+    let compute_unit_limit_instruction =
+        ComputeBudgetInstruction::set_compute_unit_limit(COMPUTE_UNIT_LIMIT);
     let compute_unit_price_instruction = ComputeBudgetInstruction::set_compute_unit_price(prio_fee);
     (
         compute_unit_limit_instruction,
@@ -49,6 +160,109 @@ pub fn get_prio_fee_instructions() -> (Instruction, Instruction) {
     )
 }
 
+/// Instructions wrapping `lamports` of native SOL into the user's WSOL
+/// associated token account, creating the account if needed.
+pub fn get_wrap_sol_instructions(
+    user: &Pubkey,
+    user_wsol_token_account: &Pubkey,
+    lamports: u64,
+) -> Result<Vec<Instruction>> {
+    Ok(vec![
+        create_associated_token_account_idempotent(user, user, &SOL, &TOKEN_PROGRAM),
+        system_instruction::transfer(user, user_wsol_token_account, lamports),
+        spl_token::instruction::sync_native(&TOKEN_PROGRAM, user_wsol_token_account)?,
+    ])
+}
+
+/// Instruction closing the user's WSOL associated token account, which
+/// unwraps its whole balance back to native SOL.
+pub fn get_unwrap_sol_instruction(
+    user: &Pubkey,
+    user_wsol_token_account: &Pubkey,
+) -> Result<Instruction> {
+    Ok(spl_token::instruction::close_account(
+        &TOKEN_PROGRAM,
+        user_wsol_token_account,
+        user,
+        user,
+        &[],
+    )?)
+}
+
+/// Instructions closing an empty token account of the owner, harvesting the
+/// Token-2022 transfer fees withheld in it to the mint first as they block
+/// the close.
+pub fn get_close_token_account_instructions(
+    owner: &Pubkey,
+    token_account: &WalletTokenAccount,
+) -> Result<Vec<Instruction>> {
+    let mut instructions = vec![];
+    if token_account.withheld_amount > 0 {
+        instructions.push(harvest_withheld_tokens_to_mint(
+            &token_account.token_program,
+            &token_account.mint,
+            &[&token_account.address],
+        )?);
+    }
+    instructions.push(spl_token_2022::instruction::close_account(
+        &token_account.token_program,
+        &token_account.address,
+        owner,
+        owner,
+        &[],
+    )?);
+    Ok(instructions)
+}
+
+/// Pool reserves in and out of a swap, the vault balances minus the pnl
+/// owed to the pool owner
+pub fn get_pool_reserves(
+    pool_info: &PoolInfo,
+    base_vault_amount: u64,
+    quote_vault_amount: u64,
+    base_in: bool,
+) -> (u64, u64) {
+    let base_reserve = base_vault_amount.saturating_sub(pool_info.base_need_take_pnl);
+    let quote_reserve = quote_vault_amount.saturating_sub(pool_info.quote_need_take_pnl);
+    if base_in {
+        (base_reserve, quote_reserve)
+    } else {
+        (quote_reserve, base_reserve)
+    }
+}
+
+/// Swap fee taken by the pool on `amount_in`, rounded up
+pub fn get_swap_fee(pool_info: &PoolInfo, amount_in: u64) -> u64 {
+    (amount_in as u128 * pool_info.swap_fee_numerator as u128)
+        .div_ceil(pool_info.swap_fee_denominator.max(1) as u128) as u64
+}
+
+/// Constant product quote of the pool for `amount_in`, net of the swap fee
+pub fn get_swap_amount_out(
+    pool_info: &PoolInfo,
+    base_vault_amount: u64,
+    quote_vault_amount: u64,
+    amount_in: u64,
+    base_in: bool,
+) -> u64 {
+    let (reserve_in, reserve_out) =
+        get_pool_reserves(pool_info, base_vault_amount, quote_vault_amount, base_in);
+    let amount_in = amount_in.saturating_sub(get_swap_fee(pool_info, amount_in)) as u128;
+    if reserve_in as u128 + amount_in == 0 {
+        return 0;
+    }
+    (reserve_out as u128 * amount_in / (reserve_in as u128 + amount_in)) as u64
+}
+
+/// AMM v4 pool address of an OpenBook market
+pub fn get_amm_id(market_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[AMM_V4.as_ref(), market_id.as_ref(), b"amm_associated_seed"],
+        &AMM_V4,
+    )
+    .0
+}
+
 pub fn get_associated_authority(program_id: Pubkey, market_id: Pubkey) -> Option<Pubkey> {
     let seeds = market_id.to_bytes();
     for nonce in 0..100 {
@@ -63,25 +277,41 @@ pub fn get_associated_authority(program_id: Pubkey, market_id: Pubkey) -> Option
     None
 }
 
+/// Fetches the pool, market and mint accounts of the pool, and the user's
+/// associated token accounts of both mints with the token program owning
+/// each mint. Both candidate token accounts of a mint are fetched in the same
+/// request, since its token program is only known once the mint is read.
 pub async fn get_accounts_for_swap(
     client: &RpcClient,
-    user_keypair: &Keypair,
+    user: &Pubkey,
     pool_init_tx_infos: PoolInitTxInfos,
-) -> Result<(PoolInfo, MarketInfo, UserTokenAccounts)> {
-    let mut account_to_create: Option<Pubkey> = None;
-
-    let user_base_token_account =
-        get_associated_token_address(&user_keypair.pubkey(), &pool_init_tx_infos.base_mint);
-    let user_quote_token_account =
-        get_associated_token_address(&user_keypair.pubkey(), &pool_init_tx_infos.quote_mint);
+) -> Result<(
+    PoolInfo,
+    MarketInfo,
+    (MintInfo, MintInfo),
+    UserTokenAccounts,
+)> {
+    let candidate_token_accounts = |mint: &Pubkey| {
+        [*TOKEN_PROGRAM, *TOKEN_2022_PROGRAM].map(|token_program| {
+            get_associated_token_address_with_program_id(user, mint, &token_program)
+        })
+    };
+    let [base_spl_token_account, base_token_2022_account] =
+        candidate_token_accounts(&pool_init_tx_infos.base_mint);
+    let [quote_spl_token_account, quote_token_2022_account] =
+        candidate_token_accounts(&pool_init_tx_infos.quote_mint);
 
     let rpc_response = client
         .get_multiple_accounts_with_config(
             &[
                 pool_init_tx_infos.amm_id,
                 pool_init_tx_infos.market_id,
-                user_base_token_account,
-                user_quote_token_account,
+                pool_init_tx_infos.base_mint,
+                pool_init_tx_infos.quote_mint,
+                base_spl_token_account,
+                base_token_2022_account,
+                quote_spl_token_account,
+                quote_token_2022_account,
             ],
             RpcAccountInfoConfig {
                 encoding: Some(UiAccountEncoding::Base64),
@@ -90,47 +320,67 @@ pub async fn get_accounts_for_swap(
                 ..RpcAccountInfoConfig::default()
             },
         )
-        .await?;
+        .await
+        .inspect_err(|_| rpc_error("getMultipleAccounts"))?;
+    let account = |index: usize| rpc_response.value.get(index).cloned().flatten();
 
-    let pool_info_account = rpc_response
-        .value
-        .first()
-        .cloned()
-        .flatten()
-        .ok_or_eyre("pool account not found")?;
+    let pool_info_account = account(0).ok_or_eyre("pool account not found")?;
     let pool_info = PoolInfo::deserialize(&mut pool_info_account.data())?;
 
-    let market_account = rpc_response
-        .value
-        .get(1)
-        .cloned()
-        .flatten()
-        .ok_or_eyre("market account not found")?;
+    let market_account = account(1).ok_or_eyre("market account not found")?;
     let market_info = MarketInfo::deserialize(&mut market_account.data())?;
 
-    match rpc_response.value.get(2).unwrap() {
-        Some(_) => tracing::info!("User's ATA for base token exists. Skipping creation.."),
-        None => {
-            tracing::info!("User's ATA for base token does not exist. Need to create..");
-            account_to_create = Some(pool_init_tx_infos.base_mint);
-        }
+    let base_mint_account = account(2).ok_or_eyre("base mint account not found")?;
+    let base_mint_info = MintInfo::from_account(pool_init_tx_infos.base_mint, &base_mint_account)?;
+
+    let quote_mint_account = account(3).ok_or_eyre("quote mint account not found")?;
+    let quote_mint_info =
+        MintInfo::from_account(pool_init_tx_infos.quote_mint, &quote_mint_account)?;
+
+    // Index of the spl-token candidate, the Token-2022 one follows it
+    let user_token_account = |mint_info: &MintInfo, index: usize| {
+        let index = if mint_info.token_program == *TOKEN_2022_PROGRAM {
+            index + 1
+        } else {
+            index
+        };
+        (
+            get_associated_token_address_with_program_id(
+                user,
+                &mint_info.mint,
+                &mint_info.token_program,
+            ),
+            account(index).is_some(),
+        )
     };
+    let (user_base_token_account, base_token_account_exists) =
+        user_token_account(&base_mint_info, 4);
+    let (user_quote_token_account, quote_token_account_exists) =
+        user_token_account(&quote_mint_info, 6);
 
-    match rpc_response.value.get(3).unwrap() {
-        Some(_) => tracing::info!("User's ATA for quote tokens exists. Skipping creation.."),
-        None => {
-            tracing::info!("User's ATA for quote token does not exist. Need to create..");
-            account_to_create = Some(pool_init_tx_infos.quote_mint);
-        }
+    let mut accounts_to_create = vec![];
+    if base_token_account_exists {
+        tracing::info!("User's ATA for base token exists. Skipping creation..");
+    } else {
+        tracing::info!("User's ATA for base token does not exist. Need to create..");
+        accounts_to_create.push(pool_init_tx_infos.base_mint);
+    }
+
+    if quote_token_account_exists {
+        tracing::info!("User's ATA for quote tokens exists. Skipping creation..");
+    } else {
+        tracing::info!("User's ATA for quote token does not exist. Need to create..");
+        accounts_to_create.push(pool_init_tx_infos.quote_mint);
     }
 
     Ok((
         pool_info,
         market_info,
+        (base_mint_info, quote_mint_info),
         UserTokenAccounts {
             user_base_token_account,
             user_quote_token_account,
-            account_to_create,
+            accounts_to_create,
         },
     ))
 }
@@ -150,7 +400,8 @@ pub async fn get_pool_and_market_info(
                 ..RpcAccountInfoConfig::default()
             },
         )
-        .await?;
+        .await
+        .inspect_err(|_| rpc_error("getMultipleAccounts"))?;
 
     let pool_account = rpc_response
         .value
@@ -167,6 +418,8 @@ pub async fn get_pool_and_market_info(
     Ok((pool_info, market_info))
 }
 
+/// Fetches and decodes token accounts, SPL Token and Token-2022 accounts
+/// alike
 pub async fn get_token_accounts(
     client: &RpcClient,
     accounts_pub_keys: &[Pubkey],
@@ -182,7 +435,7 @@ pub async fn get_token_accounts(
             },
         )
         .await
-        .unwrap()
+        .inspect_err(|_| rpc_error("getMultipleAccounts"))?
         .value
         .into_iter()
         .collect::<Option<Vec<_>>>()
@@ -192,37 +445,80 @@ pub async fn get_token_accounts(
         return Err(eyre!("Token accounts not found"));
     }
 
-    let account = accounts
-        .into_iter()
-        .map(|a| TokenAccount::deserialize(&mut a.data.as_slice()))
+    let account = accounts_pub_keys
+        .iter()
+        .zip(accounts)
+        .map(|(address, account)| TokenAccount::from_account(address, &account))
         .collect::<Result<Vec<_>, _>>()?;
 
     Ok(account)
 }
 
-/// Fetches the serum marketID of the pool
-pub async fn get_market_id(
+/// Fetches the SPL Token and Token-2022 accounts owned by the wallet
+pub async fn get_wallet_token_accounts(
+    client: &RpcClient,
+    owner: &Pubkey,
+) -> Result<Vec<WalletTokenAccount>> {
+    let mut token_accounts = vec![];
+    for token_program in [*TOKEN_PROGRAM, *TOKEN_2022_PROGRAM] {
+        let keyed_accounts = client
+            .get_token_accounts_by_owner(owner, TokenAccountsFilter::ProgramId(token_program))
+            .await?;
+
+        for keyed_account in keyed_accounts {
+            let UiAccountData::Json(parsed_account) = keyed_account.account.data else {
+                return Err(eyre!(
+                    "token account {} is not parsed",
+                    keyed_account.pubkey
+                ));
+            };
+            let info = &parsed_account.parsed["info"];
+            let mint = info["mint"]
+                .as_str()
+                .ok_or_eyre("token account without mint")?;
+            let token_amount: UiTokenAmount = serde_json::from_value(info["tokenAmount"].clone())?;
+            let withheld_amount = info["extensions"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .find(|extension| extension["extension"] == "transferFeeAmount")
+                .and_then(|extension| extension["state"]["withheldAmount"].as_u64())
+                .unwrap_or(0);
+
+            token_accounts.push(WalletTokenAccount {
+                address: Pubkey::from_str(&keyed_account.pubkey)?,
+                mint: Pubkey::from_str(mint)?,
+                token_program,
+                amount: token_amount.amount.parse()?,
+                decimals: token_amount.decimals,
+                ui_amount: token_amount.ui_amount_string,
+                withheld_amount,
+            });
+        }
+    }
+    Ok(token_accounts)
+}
+
+/// Looks up the OpenBook market of the pair in both directions, `None` when
+/// the pair has no market
+pub async fn find_market_id(
     rpc_client: &RpcClient,
     base_mint_address: &str,
     target_mint_address: &str,
-) -> Pubkey {
+) -> Result<Option<Pubkey>> {
     let candidate_market_id =
-        get_candidate_market_id(rpc_client, base_mint_address, target_mint_address).await;
-    if let Some((market_id, _)) = candidate_market_id {
-        market_id
-    } else {
-        get_candidate_market_id(rpc_client, target_mint_address, base_mint_address)
-            .await
-            .unwrap()
-            .0
+        get_candidate_market_id(rpc_client, base_mint_address, target_mint_address).await?;
+    if candidate_market_id.is_some() {
+        return Ok(candidate_market_id);
     }
+    get_candidate_market_id(rpc_client, target_mint_address, base_mint_address).await
 }
 
 async fn get_candidate_market_id(
     rpc_client: &RpcClient,
     base_mint_address: &str,
     target_mint_address: &str,
-) -> Option<(Pubkey, solana_sdk::account::Account)> {
+) -> Result<Option<Pubkey>> {
     const BASEMINT_OFFSET: usize = 53; // offset of 'BaseMint'
     let base_mint_memcmp = RpcFilterType::Memcmp(Memcmp::new(
         BASEMINT_OFFSET,
@@ -235,7 +531,7 @@ async fn get_candidate_market_id(
         MemcmpEncodedBytes::Base58(target_mint_address.to_string()),
     ));
 
-    rpc_client
+    Ok(rpc_client
         .get_program_accounts_with_config(
             &OPENBOOK,
             RpcProgramAccountsConfig {
@@ -248,8 +544,9 @@ async fn get_candidate_market_id(
             },
         )
         .await
-        .unwrap()
+        .inspect_err(|_| rpc_error("getProgramAccounts"))?
         .pop()
+        .map(|(market_id, _)| market_id))
 }
 
 pub async fn get_transaction_from_signature(
@@ -262,6 +559,7 @@ pub async fn get_transaction_from_signature(
         .await;
 
     if get_transaction_result.is_err() {
+        rpc_error("getTransaction");
         return Err(eyre!(
             "failed to get transaction: {:?}",
             get_transaction_result.err()
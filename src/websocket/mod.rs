@@ -14,6 +14,8 @@ use tokio_tungstenite::{
 };
 use url::Url;
 
+use crate::metrics::WEBSOCKET_RECONNECTS;
+
 #[allow(dead_code)]
 pub struct Uninitialized;
 #[allow(dead_code)]
@@ -50,6 +52,7 @@ impl WebSocket<Initialized> {
                 tracing::warn!("connection lost: {}", read_result.err().unwrap());
                 let _ = self.socket.as_mut().unwrap().close(None).await;
                 let _ = self.socket.as_mut().unwrap().flush().await;
+                WEBSOCKET_RECONNECTS.inc();
                 self.reconnect().await?;
                 self.config.num_retries -= 1;
                 continue;
@@ -196,7 +199,7 @@ async fn subscribe(
 }
 
 #[allow(unused)]
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct LogsSubscribeResponse {
     pub jsonrpc: String,
     pub method: String,
@@ -204,7 +207,7 @@ pub struct LogsSubscribeResponse {
 }
 
 #[allow(unused)]
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct SubscribeResponseParams {
     pub subscription: u32,
     pub result: Response<RpcLogsResponse>,